@@ -4,6 +4,12 @@ fn main() {
     let args: Vec<_> = std::env::args().collect();
     let regex = &args[1];
     //println!("{}", regex);
-    let minimized_dfa = Automaton::from_string(&regex).unwrap().into_min_dfa();
+    let minimized_dfa = match Automaton::from_string(regex) {
+        Ok(automaton) => automaton.into_min_dfa(),
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
     println!("{}", minimized_dfa.to_dot_format());
 }
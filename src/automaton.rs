@@ -1,21 +1,395 @@
-use std::collections::{BTreeSet, HashMap, VecDeque};
-use std::ops::Range;
+use crate::captures::{Captures, TaggedDfa};
 use crate::translator;
+use num_bigint::BigUint;
+use regex_syntax::hir::{self, HirKind};
+use std::borrow::Cow;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::convert::Infallible;
+use std::hash::Hash;
+use std::ops::Range;
+use std::sync::OnceLock;
+
+type Symbols = BTreeSet<Option<CharRange>>;
+
+/// A state of an nfa, composed with what was read right before reaching it
+type LookState = (BTreeSet<u32>, LookContext);
+
+/// A state of a product of two dfas, composed of a state of each, where `None` is the dead state
+type ProductPair = (Option<u32>, Option<u32>);
+
+/// An inclusive range of unicode scalar values, used as the label of a transition
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CharRange {
+    pub start: char,
+    pub end: char,
+}
+
+impl CharRange {
+    pub fn new(start: char, end: char) -> Self {
+        Self { start, end }
+    }
+
+    pub fn single(c: char) -> Self {
+        Self::new(c, c)
+    }
+
+    /// Returns the number of symbols in the range, which skips the surrogates between ```\u{D7FF}``` and ```\u{E000}```
+    pub fn len(&self) -> u32 {
+        if self.end < self.start {
+            return 0;
+        }
+        let surrogates = if self.start < '\u{E000}' && self.end > '\u{D7FF}' {
+            0x800
+        } else {
+            0
+        };
+        self.end as u32 - self.start as u32 + 1 - surrogates
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains(&self, c: char) -> bool {
+        self.start <= c && c <= self.end
+    }
+}
+
+impl std::fmt::Display for CharRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.start == self.end {
+            write!(f, "{}", self.start.escape_debug())
+        } else {
+            write!(
+                f,
+                "{}-{}",
+                self.start.escape_debug(),
+                self.end.escape_debug()
+            )
+        }
+    }
+}
+
+/// A zero-width assertion about the symbols around a position in the input,
+/// used as the label of a look transition
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Look {
+    /// Holds at the start of the input, like ```\A```
+    StartText,
+    /// Holds at the end of the input, like ```\z```
+    EndText,
+    /// Holds at the start of the input or after ```\n```, like ```(?m)^```
+    StartLine,
+    /// Holds at the end of the input or before ```\n```, like ```(?m)$```
+    EndLine,
+    /// Holds between a unicode word character and anything else, like ```\b```
+    WordBoundary,
+    /// Holds wherever [`Look::WordBoundary`] does not, like ```\B```
+    NotWordBoundary,
+    /// Holds between an ascii word character and anything else, like ```(?-u)\b```
+    WordBoundaryAscii,
+    /// Holds wherever [`Look::WordBoundaryAscii`] does not, like ```(?-u)\B```
+    NotWordBoundaryAscii,
+}
+
+impl Look {
+    /// Returns `true` if the assertion holds between a symbol described by `behind`
+    /// and a symbol described by `ahead`
+    pub(crate) fn holds(self, behind: LookContext, ahead: LookContext) -> bool {
+        match self {
+            Look::StartText => behind == LookContext::TextBoundary,
+            Look::EndText => ahead == LookContext::TextBoundary,
+            Look::StartLine => behind.is_line_boundary(),
+            Look::EndLine => ahead.is_line_boundary(),
+            Look::WordBoundary => behind.is_word() != ahead.is_word(),
+            Look::NotWordBoundary => behind.is_word() == ahead.is_word(),
+            Look::WordBoundaryAscii => behind.is_ascii_word() != ahead.is_ascii_word(),
+            Look::NotWordBoundaryAscii => behind.is_ascii_word() == ahead.is_ascii_word(),
+        }
+    }
+
+    /// Returns the symbols where the outcome of the assertion may change,
+    /// so that every range of symbols in an alphabet containing them is described by a single [`LookContext`]
+    fn boundaries(self) -> Vec<char> {
+        let ranges = match self {
+            Look::StartText | Look::EndText => Vec::new(),
+            Look::StartLine | Look::EndLine => vec![CharRange::single('\n')],
+            Look::WordBoundary | Look::NotWordBoundary => word_ranges(true),
+            Look::WordBoundaryAscii | Look::NotWordBoundaryAscii => word_ranges(false),
+        };
+        ranges
+            .iter()
+            .flat_map(|range| std::iter::once(range.start).chain(next_char(range.end)))
+            .collect()
+    }
+}
+
+/// Describes the symbol on one side of a position in the input, as far as any [`Look`] is concerned
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) enum LookContext {
+    /// There is no symbol, the position is at the start or the end of the input
+    TextBoundary,
+    LineFeed,
+    AsciiWord,
+    /// A word character that is not ascii
+    UnicodeWord,
+    Other,
+}
+
+impl LookContext {
+    pub(crate) const ALL: [LookContext; 5] = [
+        LookContext::TextBoundary,
+        LookContext::LineFeed,
+        LookContext::AsciiWord,
+        LookContext::UnicodeWord,
+        LookContext::Other,
+    ];
+
+    /// Returns the contexts that can be told apart in what was read before a position.
+    /// Without looks, what was read before never matters, so only [`LookContext::TextBoundary`] is returned.
+    pub(crate) fn behind_contexts(has_looks: bool) -> &'static [LookContext] {
+        if has_looks {
+            &LookContext::ALL
+        } else {
+            &[LookContext::TextBoundary]
+        }
+    }
+
+    pub(crate) fn of(atom: Option<char>) -> Self {
+        match atom {
+            None => LookContext::TextBoundary,
+            Some('\n') => LookContext::LineFeed,
+            Some(c) if c.is_ascii() && regex_syntax::is_word_byte(c as u8) => {
+                LookContext::AsciiWord
+            }
+            Some(c) if regex_syntax::is_word_character(c) => LookContext::UnicodeWord,
+            Some(_) => LookContext::Other,
+        }
+    }
+
+    fn is_line_boundary(self) -> bool {
+        matches!(self, LookContext::TextBoundary | LookContext::LineFeed)
+    }
+
+    fn is_word(self) -> bool {
+        matches!(self, LookContext::AsciiWord | LookContext::UnicodeWord)
+    }
+
+    fn is_ascii_word(self) -> bool {
+        self == LookContext::AsciiWord
+    }
+}
+
+/// How a dfa built from an nfa with look transitions depends on the symbols around the input it reads
+#[derive(Debug, Clone)]
+struct LookTable {
+    /// The state to start from, given the symbol right before the start of the input
+    start_states: BTreeMap<LookContext, u32>,
+    /// For every state that is accepting when the input continues after it,
+    /// the contexts of the next symbol for which it is accepting
+    accepting_before: BTreeMap<u32, BTreeSet<LookContext>>,
+}
+
+/// The tagged dfa of a dfa built from an nfa with capture groups, which is only built the first time it is used,
+/// since most matches do not need it and it can be much larger than the dfa
+#[derive(Debug, Clone)]
+struct LazyTaggedDfa {
+    nfa: Box<Automaton>,
+    /// The limits the dfa was built with, which apply to the tagged dfa on its own
+    limits: DfaLimits,
+    tagged: OnceLock<Result<Box<TaggedDfa>, LimitError>>,
+}
+
+impl LazyTaggedDfa {
+    fn get(&self) -> Result<&TaggedDfa, LimitError> {
+        self.tagged
+            .get_or_init(|| {
+                TaggedDfa::new(&self.nfa, false, &mut Budget::new(&self.limits)).map(Box::new)
+            })
+            .as_deref()
+            .map_err(|error| *error)
+    }
+}
+
+/// Which match is found by [`Automaton::match_prefix`] and [`Automaton::match_leftmost`]
+/// when a string can be matched in more than one way
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MatchKind {
+    /// The longest match is found, as in posix, ignoring whether repetitions are greedy
+    /// and the order of the alternatives of an alternation
+    #[default]
+    LeftmostLongest,
+    /// The match that a backtracking engine like perl would find first, which prefers
+    /// the earlier alternatives of an alternation, repeating greedy repetitions as much as possible
+    /// and lazy repetitions like ```a+?``` as little as possible.
+    ///
+    /// A match does not end as soon as an accepting state is reached, but where such an engine would stop,
+    /// so greedy ```a+``` matches all of ```aaa```, while lazy ```a+?``` only matches the first ```a```.
+    LeftmostFirst,
+}
+
+/// Limits on the size of the dfa built by [`Automaton::into_dfa_with_limits`] and
+/// [`Automaton::into_min_dfa_with_limits`].
+///
+/// The smallest dfa simulating a regular expression can be exponentially larger than the regular expression,
+/// like for ```(a|b)*a(a|b){20}```, which needs millions of states.
+///
+/// The dfas that find the captures of [`Automaton::captures`] and the matches of [`MatchKind::LeftmostFirst`]
+/// are each limited on their own by the same limits, so they do not count towards the limits of the dfa.
+#[derive(Debug, Clone)]
+pub struct DfaLimits {
+    /// The largest number of states the dfa may have
+    pub max_states: usize,
+    /// The largest number of transitions the dfa may have, where every transition reads a range of symbols
+    pub max_transitions: usize,
+    /// The largest (approximate) number of bytes that may be used while building and minimizing the dfa
+    pub max_bytes: usize,
+}
+
+impl DfaLimits {
+    fn unlimited() -> Self {
+        Self {
+            max_states: usize::MAX,
+            max_transitions: usize::MAX,
+            max_bytes: usize::MAX,
+        }
+    }
+}
+
+impl Default for DfaLimits {
+    fn default() -> Self {
+        Self {
+            max_states: 10_000,
+            max_transitions: 1_000_000,
+            max_bytes: 256 * 1024 * 1024,
+        }
+    }
+}
 
-type Symbols = BTreeSet<Option<char>>;
+/// An error returned when building a dfa would exceed one of its [`DfaLimits`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitError {
+    /// The dfa would have more states than the contained [`DfaLimits::max_states`]
+    StatesExceeded(usize),
+    /// The dfa would have more transitions than the contained [`DfaLimits::max_transitions`]
+    TransitionsExceeded(usize),
+    /// Building the dfa would use more bytes than the contained [`DfaLimits::max_bytes`]
+    BytesExceeded(usize),
+}
 
-#[derive(Debug)]
+impl std::fmt::Display for LimitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            LimitError::StatesExceeded(limit) => {
+                write!(f, "the dfa exceeds the limit of {} states", limit)
+            }
+            LimitError::TransitionsExceeded(limit) => {
+                write!(f, "the dfa exceeds the limit of {} transitions", limit)
+            }
+            LimitError::BytesExceeded(limit) => {
+                write!(f, "building the dfa exceeds the limit of {} bytes", limit)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LimitError {}
+
+/// An error returned when building an nfa from a dfa with assertions like ```\b```,
+/// whose states depend on the symbols around its input, which an nfa cannot keep track of
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LookError;
+
+impl std::fmt::Display for LookError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "the dfa depends on the symbols around its input")
+    }
+}
+
+impl std::error::Error for LookError {}
+
+/// Keeps track of the size of a dfa while it is being built
+pub(crate) struct Budget<'a> {
+    limits: &'a DfaLimits,
+    states: usize,
+    transitions: usize,
+    bytes: usize,
+}
+
+impl<'a> Budget<'a> {
+    /// The approximate number of bytes used for a state, besides the nfa states it is composed of
+    const STATE_BYTES: usize = 64;
+    /// The approximate number of bytes used for a transition, which is stored from both of its states
+    const TRANSITION_BYTES: usize = 64;
+
+    pub(crate) fn new(limits: &'a DfaLimits) -> Self {
+        Self {
+            limits,
+            states: 0,
+            transitions: 0,
+            bytes: 0,
+        }
+    }
+
+    /// Adds a state composed of `composite_states` nfa states
+    pub(crate) fn add_state(&mut self, composite_states: usize) -> Result<(), LimitError> {
+        self.states += 1;
+        if self.states > self.limits.max_states {
+            return Err(LimitError::StatesExceeded(self.limits.max_states));
+        }
+        self.add_bytes(Self::STATE_BYTES + composite_states * std::mem::size_of::<u32>())
+    }
+
+    pub(crate) fn add_transition(&mut self) -> Result<(), LimitError> {
+        self.transitions += 1;
+        if self.transitions > self.limits.max_transitions {
+            return Err(LimitError::TransitionsExceeded(self.limits.max_transitions));
+        }
+        self.add_bytes(Self::TRANSITION_BYTES)
+    }
+
+    fn add_bytes(&mut self, bytes: usize) -> Result<(), LimitError> {
+        self.bytes = self.bytes.saturating_add(bytes);
+        if self.bytes > self.limits.max_bytes {
+            return Err(LimitError::BytesExceeded(self.limits.max_bytes));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Automaton {
     pub states: u32,
     pub from_transitions: HashMap<u32, HashMap<u32, Symbols>>,
     pub to_transitions: HashMap<u32, HashMap<u32, Symbols>>,
     pub start_state: u32,
     pub accepting_states: BTreeSet<u32>,
+    /// The first symbol of every range of symbols that are always traversed together.
+    ///
+    /// Each symbol in the alphabet stands in for all symbols up to (but not including) the next
+    /// symbol in the alphabet, so traversing via the alphabet visits every distinct transition.
     alphabet: BTreeSet<char>,
+    /// Transitions that can only be traversed without reading any input where their [`Look`] holds
+    pub(crate) look_transitions: HashMap<u32, Vec<(u32, Look)>>,
+    /// The epsilon transitions from every state, in order of priority, with the tag each of them sets, if any.
+    ///
+    /// Tag 2n marks the start and tag 2n+1 marks the end of capture group n.
+    pub(crate) ordered_epsilons: HashMap<u32, Vec<(u32, Option<usize>)>>,
+    /// The name of every capture group, if it has one, where group 0 is the whole match
+    pub(crate) capture_names: Vec<Option<String>>,
+    /// Only set for dfas built from an nfa with capture groups
+    tagged: Option<Box<LazyTaggedDfa>>,
+    match_kind: MatchKind,
+    /// Only set for dfas built from an nfa with [`MatchKind::LeftmostFirst`]
+    prioritized: Option<Box<TaggedDfa>>,
+    /// Only set for dfas built from an nfa with look transitions,
+    /// whose accepting states are the ones that are accepting at the end of the input
+    looks: Option<LookTable>,
     kind: AutomatonKind,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 enum AutomatonKind {
     Nfa,
     Dfa,
@@ -31,30 +405,61 @@ impl Automaton {
             start_state: 0, // the dead state
             accepting_states: BTreeSet::new(),
             alphabet: BTreeSet::new(),
+            look_transitions: HashMap::new(),
+            ordered_epsilons: HashMap::new(),
+            capture_names: Vec::new(),
+            tagged: None,
+            match_kind: MatchKind::LeftmostLongest,
+            prioritized: None,
+            looks: None,
             kind: AutomatonKind::Nfa,
         }
     }
 
     pub fn from_string(s: &str) -> Result<Automaton, translator::TranslatorError> {
-        let automaton = translator::translate(s)?;
+        Self::from_string_with_config(s, &translator::TranslatorConfig::default())
+    }
+
+    pub fn from_string_with_config(
+        s: &str,
+        config: &translator::TranslatorConfig,
+    ) -> Result<Automaton, translator::TranslatorError> {
+        let automaton = translator::translate(s, config)?;
         Ok(automaton)
     }
 
     pub fn into_dfa(self) -> Automaton {
+        // Without limits, building the dfa never fails
+        self.into_dfa_with_limits(&DfaLimits::unlimited())
+            .unwrap_or_default()
+    }
+
+    pub fn into_min_dfa(self) -> Automaton {
+        // Without limits, building the dfa never fails
+        self.into_min_dfa_with_limits(&DfaLimits::unlimited())
+            .unwrap_or_default()
+    }
+
+    /// Like [`Automaton::into_dfa`], but fails instead of building a dfa that exceeds `limits`
+    pub fn into_dfa_with_limits(self, limits: &DfaLimits) -> Result<Automaton, LimitError> {
         match self.kind {
-            AutomatonKind::Nfa => nfa_to_dfa(&self),
-            _ => self,
+            AutomatonKind::Nfa => nfa_to_dfa(&self, &mut Budget::new(limits)),
+            _ => Ok(self),
         }
     }
 
-    pub fn into_min_dfa(self) -> Automaton {
+    /// Like [`Automaton::into_min_dfa`], but fails instead of building a dfa that exceeds `limits`.
+    /// The limits apply to the dfa built before minimizing it, and to the classes of equivalent states
+    /// used to minimize it.
+    pub fn into_min_dfa_with_limits(self, limits: &DfaLimits) -> Result<Automaton, LimitError> {
         match self.kind {
             AutomatonKind::Nfa => {
-                let dfa = nfa_to_dfa(&self);
-                dfa_to_minimized_dfa(&dfa)
+                let mut budget = Budget::new(limits);
+                let dfa = nfa_to_dfa(&self, &mut budget)?;
+                dfa_to_minimized_dfa(&dfa, &mut budget)
             }
-            AutomatonKind::Dfa => dfa_to_minimized_dfa(&self),
-            _ => self,
+            AutomatonKind::Dfa => dfa_to_minimized_dfa(&self, &mut Budget::new(limits)),
+            _ => Ok(self),
         }
     }
 
@@ -85,8 +490,8 @@ impl Automaton {
     /// Traverses the dfa via the characters in `input` to find the first prefix that is matched by the dfa
     pub fn match_first_prefix<'a>(&self, input: &'a str) -> Option<&'a str> {
         let mut current_state = self.start_state;
-        for (index, current_atom) in input.chars().enumerate() {
-            if self.accepting_states.contains(&current_state) {
+        for (index, current_atom) in input.char_indices() {
+            if self.is_accepting_before(current_state, Some(current_atom)) {
                 return Some(&input[0..index]);
             }
             match self.traverse_from(current_state, current_atom) {
@@ -94,24 +499,29 @@ impl Automaton {
                 None => return None,
             }
         }
-        Some(input)
+        if self.accepting_states.contains(&current_state) {
+            Some(input)
+        } else {
+            None
+        }
     }
 
-    fn match_all_prefixes(&self, input: &str) -> Vec<Range<usize>> {
+    /// Returns the ranges of all matches in `input` that begin at the byte offset `start`
+    fn match_all_prefixes(&self, input: &str, start: usize) -> Vec<Range<usize>> {
         let mut matched_prefixes = Vec::new();
 
-        let mut current_state = self.start_state;
-        if self.accepting_states.contains(&current_state) {
-            matched_prefixes.push(Range { start: 0, end: 0 });
-        }
-        for (index, current_atom) in input.chars().enumerate() {
+        let mut current_state = self.start_state_at(input, start);
+        for (index, current_atom) in input[start..].char_indices() {
+            if self.is_accepting_before(current_state, Some(current_atom)) {
+                matched_prefixes.push(start..start + index);
+            }
             match self.traverse_from(current_state, current_atom) {
                 Some(next_state) => current_state = next_state,
                 None => return matched_prefixes,
             }
-            if self.accepting_states.contains(&current_state) {
-                matched_prefixes.push(0..index + 1);
-            }
+        }
+        if self.accepting_states.contains(&current_state) {
+            matched_prefixes.push(start..input.len());
         }
         matched_prefixes
     }
@@ -119,48 +529,175 @@ impl Automaton {
     pub fn match_substrings(&self, input: &str) -> Vec<Range<usize>> {
         let mut matched_substrings = Vec::new();
 
-        for (index, _) in input.chars().enumerate() {
-            let matched_prefixes = self.match_all_prefixes(&input[index..]);
-            for range in matched_prefixes {
-                matched_substrings.push(range.start + index..range.end + index);
-            }
+        for (index, _) in input.char_indices() {
+            matched_substrings.extend(self.match_all_prefixes(input, index));
         }
         matched_substrings
     }
 
     pub fn match_longest_prefix(&self, input: &str) -> Option<Range<usize>> {
-        let mut longest_match = None;
+        self.match_all_prefixes(input, 0).pop()
+    }
 
-        let mut current_state = self.start_state;
-        for (index, current_atom) in input.chars().enumerate() {
-            if self.accepting_states.contains(&current_state) {
-                longest_match = Some(0..index + 1);
+    pub fn match_longest_substring(&self, input: &str) -> Option<Range<usize>> {
+        let mut longest_substring = None;
+
+        for (index, _) in input.char_indices() {
+            if let Some(substring) = self.match_all_prefixes(input, index).pop() {
+                if substring.len() > longest_substring.as_ref().unwrap_or(&(0..0)).len() {
+                    longest_substring = Some(substring)
+                }
             }
-            match self.traverse_from(current_state, current_atom) {
-                Some(next_state) => current_state = next_state,
-                None => break,
+        }
+        longest_substring
+    }
+
+    /// Matches the whole of `input`, like [`Automaton::match_whole`], and returns the span of every capture group,
+    /// like ```(a)``` or ```(?P<name>a)```. If a group can match in more than one way, the alternatives of an alternation
+    /// are preferred in order, greedy repetitions are preferred to match as much as possible and lazy repetitions
+    /// as little as possible. A repeated group captures its last iteration.
+    ///
+    /// The captures are found in a single pass over `input`, by a dfa that keeps track of where each group starts
+    /// and ends. A dfa builds it the first time this is called, within the limits the dfa was built with,
+    /// and an nfa builds it every time this is called, within the default [`DfaLimits`].
+    /// Returns `None` if it would exceed them, which [`Automaton::try_captures`] reports instead.
+    pub fn captures(&self, input: &str) -> Option<Captures> {
+        self.try_captures(input).ok().flatten()
+    }
+
+    /// Like [`Automaton::captures`], but fails if the dfa that finds the captures would exceed its limits
+    pub fn try_captures(&self, input: &str) -> Result<Option<Captures>, LimitError> {
+        match (&self.kind, &self.tagged) {
+            (AutomatonKind::Nfa, _) => {
+                let tagged = TaggedDfa::new(self, false, &mut Budget::new(&DfaLimits::default()))?;
+                Ok(tagged.captures(input))
             }
+            (_, Some(tagged)) => Ok(tagged.get()?.captures(input)),
+            (_, None) if self.match_whole(input) => Ok(Some(Captures::whole(input))),
+            (_, None) => Ok(None),
         }
-        longest_match
     }
 
-    pub fn match_longest_substring(&self, input: &str) -> Option<Range<usize>> {
-        let mut longest_substring = None;
+    /// Returns the match that begins at the start of `input`, if any,
+    /// which is the longest or the preferred match depending on the [`MatchKind`].
+    ///
+    /// An nfa with [`MatchKind::LeftmostFirst`] builds the dfa that finds its preferred matches
+    /// within the default [`DfaLimits`], and finds no match if it would exceed them,
+    /// which [`Automaton::try_match_prefix`] reports instead.
+    pub fn match_prefix(&self, input: &str) -> Option<Range<usize>> {
+        self.try_match_prefix(input).ok().flatten()
+    }
+
+    /// Like [`Automaton::match_prefix`], but fails if the dfa that finds the preferred matches would exceed its limits
+    pub fn try_match_prefix(&self, input: &str) -> Result<Option<Range<usize>>, LimitError> {
+        let prioritized = self.prioritized_dfa()?;
+        Ok(self.match_prefix_at(input, 0, prioritized.as_deref()))
+    }
+
+    /// Returns the match that begins the earliest in `input`, if any,
+    /// which is the longest or the preferred match that begins there depending on the [`MatchKind`].
+    /// Like [`Automaton::match_prefix`], an nfa with [`MatchKind::LeftmostFirst`] finds no match
+    /// if its dfa would exceed the default [`DfaLimits`], which [`Automaton::try_match_leftmost`] reports instead.
+    pub fn match_leftmost(&self, input: &str) -> Option<Range<usize>> {
+        self.try_match_leftmost(input).ok().flatten()
+    }
+
+    /// Like [`Automaton::match_leftmost`], but fails if the dfa that finds the preferred matches would exceed its limits
+    pub fn try_match_leftmost(&self, input: &str) -> Result<Option<Range<usize>>, LimitError> {
+        let prioritized = self.prioritized_dfa()?;
+        Ok(input
+            .char_indices()
+            .map(|(index, _)| index)
+            .chain(std::iter::once(input.len()))
+            .find_map(|start| self.match_prefix_at(input, start, prioritized.as_deref())))
+    }
 
-        for (index, _) in input.chars().enumerate() {
-            if let Some(prefix) = self.match_longest_prefix(&input[index..]) {
-                if prefix.len() > longest_substring.as_ref().unwrap_or(&(0..0)).len() {
-                    longest_substring = Some(prefix.start + index..prefix.end + index)
+    pub fn match_kind(&self) -> MatchKind {
+        self.match_kind
+    }
+
+    /// Sets which match is found when a string can be matched in more than one way.
+    /// Since a dfa does not know how its strings are matched, this must be set before the dfa is built,
+    /// and does nothing for a dfa.
+    pub fn set_match_kind(&mut self, match_kind: MatchKind) {
+        if let AutomatonKind::Nfa = self.kind {
+            self.match_kind = match_kind;
+        }
+    }
+
+    /// Returns the dfa that finds the preferred matches if the [`MatchKind`] is [`MatchKind::LeftmostFirst`],
+    /// which an nfa builds within the default [`DfaLimits`]
+    fn prioritized_dfa(&self) -> Result<Option<Cow<'_, TaggedDfa>>, LimitError> {
+        match (self.match_kind, &self.kind, &self.prioritized) {
+            (MatchKind::LeftmostLongest, _, _) => Ok(None),
+            (MatchKind::LeftmostFirst, AutomatonKind::Nfa, _) => {
+                let prioritized =
+                    TaggedDfa::new(self, true, &mut Budget::new(&DfaLimits::default()))?;
+                Ok(Some(Cow::Owned(prioritized)))
+            }
+            (MatchKind::LeftmostFirst, _, prioritized) => {
+                Ok(prioritized.as_deref().map(Cow::Borrowed))
+            }
+        }
+    }
+
+    /// Returns the match that begins at the byte offset `start` in `input`,
+    /// found by `prioritized` if it is the dfa that finds the preferred matches, or else the longest match
+    fn match_prefix_at(
+        &self,
+        input: &str,
+        start: usize,
+        prioritized: Option<&TaggedDfa>,
+    ) -> Option<Range<usize>> {
+        match prioritized {
+            Some(prioritized) => prioritized.match_prefix(input, start).map(|end| start..end),
+            None => self.match_all_prefixes(input, start).pop(),
+        }
+    }
+
+    /// Returns the state to start matching from at the byte offset `index` in `input`,
+    /// which depends on the symbol before it if the dfa has look transitions
+    fn start_state_at(&self, input: &str, index: usize) -> u32 {
+        self.start_state_after(LookContext::of(input[..index].chars().next_back()))
+    }
+
+    /// Returns `true` if `state` is accepting when the next symbol of the input is `next`,
+    /// or when the input ends if `next` is `None`
+    fn is_accepting_before(&self, state: u32, next: Option<char>) -> bool {
+        match (&self.looks, next) {
+            (Some(looks), Some(_)) => looks
+                .accepting_before
+                .get(&state)
+                .is_some_and(|contexts| contexts.contains(&LookContext::of(next))),
+            _ => self.accepting_states.contains(&state),
+        }
+    }
+
+    /// Returns the contexts of the next symbol for which `state` is accepting,
+    /// where [`LookContext::TextBoundary`] means that the input ends
+    fn accepting_contexts(&self, state: u32) -> BTreeSet<LookContext> {
+        let is_accepting = self.accepting_states.contains(&state);
+        match &self.looks {
+            Some(looks) => {
+                let mut contexts = looks
+                    .accepting_before
+                    .get(&state)
+                    .cloned()
+                    .unwrap_or_default();
+                if is_accepting {
+                    contexts.insert(LookContext::TextBoundary);
                 }
+                contexts
             }
+            None if is_accepting => LookContext::ALL.iter().copied().collect(),
+            None => BTreeSet::new(),
         }
-        longest_substring
     }
 
     fn traverse_from(&self, from_state: u32, atom: char) -> Option<u32> {
         if let Some(transitions) = self.from_transitions.get(&from_state) {
             for (to_state, atoms_set) in transitions {
-                if atoms_set.contains(&Some(atom)) {
+                if contains_atom(atoms_set, atom) {
                     return Some(*to_state);
                 }
             }
@@ -173,15 +710,38 @@ impl Automaton {
 
         // Add states and transitions to concatenated dfa
         self.add_states(other_dfa.states);
+        // The epsilon transitions are added first, so that their order of priority is kept
+        for (from_state, to_states) in other_dfa.ordered_epsilons {
+            for (to_state, tag) in to_states {
+                self.add_epsilon_transition(
+                    from_state + states_offset,
+                    to_state + states_offset,
+                    tag,
+                );
+            }
+        }
         for from_transition in other_dfa.from_transitions {
             let from_state = from_transition.0;
             for to_states in from_transition.1 {
                 let to_state = to_states.0;
                 for atom in to_states.1 {
-                    self.add_transition(from_state + states_offset, to_state + states_offset, atom);
+                    self.add_symbol_transition(
+                        from_state + states_offset,
+                        to_state + states_offset,
+                        atom,
+                    );
                 }
             }
         }
+        for (from_state, to_states) in other_dfa.look_transitions {
+            for (to_state, look) in to_states {
+                self.add_look_transition(
+                    from_state + states_offset,
+                    to_state + states_offset,
+                    look,
+                );
+            }
+        }
     }
 
     pub fn add_state(&mut self) -> u32 {
@@ -196,11 +756,78 @@ impl Automaton {
     }
 
     pub fn add_transition(&mut self, from_state: u32, to_state: u32, atom: Option<char>) {
-        if let Some(c) = atom {
-            self.alphabet.insert(c);
+        self.add_symbol_transition(from_state, to_state, atom.map(CharRange::single));
+    }
+
+    /// Adds a transition from `from_state` to `to_state` via every symbol in `range`,
+    /// or does nothing if `range` is empty, like a reversed range
+    pub fn add_range_transition(&mut self, from_state: u32, to_state: u32, range: CharRange) {
+        if range.is_empty() {
+            return;
+        }
+        self.add_symbol_transition(from_state, to_state, Some(range));
+    }
+
+    /// Adds a transition from `from_state` to `to_state` that does not read any input,
+    /// and can only be traversed where `look` holds
+    pub fn add_look_transition(&mut self, from_state: u32, to_state: u32, look: Look) {
+        if from_state >= self.states || to_state >= self.states {
+            return;
+        }
+        self.look_transitions
+            .entry(from_state)
+            .or_default()
+            .push((to_state, look));
+        self.kind = AutomatonKind::Nfa;
+    }
+
+    /// Adds an epsilon transition from `from_state` to `to_state` that sets `tag` to the current position
+    /// when it is traversed. Tag 2n marks the start and tag 2n+1 marks the end of capture group n.
+    ///
+    /// Epsilon transitions from the same state are preferred in the order they are added,
+    /// which decides the captures of strings that can be matched in more than one way.
+    pub fn add_tagged_transition(&mut self, from_state: u32, to_state: u32, tag: usize) {
+        self.add_epsilon_transition(from_state, to_state, Some(tag));
+    }
+
+    /// Sets the name of every capture group, where group 0 is the whole match
+    pub fn set_capture_names(&mut self, capture_names: Vec<Option<String>>) {
+        self.capture_names = capture_names;
+    }
+
+    pub fn capture_names(&self) -> &[Option<String>] {
+        &self.capture_names
+    }
+
+    fn add_epsilon_transition(&mut self, from_state: u32, to_state: u32, tag: Option<usize>) {
+        if from_state >= self.states || to_state >= self.states {
+            return;
+        }
+        let ordered_epsilons = self.ordered_epsilons.entry(from_state).or_default();
+        if !ordered_epsilons.contains(&(to_state, tag)) {
+            ordered_epsilons.push((to_state, tag));
+        }
+        self.add_from_transition(from_state, to_state, None);
+        self.add_to_transition(from_state, to_state, None);
+        self.kind = AutomatonKind::Nfa;
+    }
+
+    /// Transitions from or to states that have not been added are ignored
+    fn add_symbol_transition(&mut self, from_state: u32, to_state: u32, symbol: Option<CharRange>) {
+        if from_state >= self.states || to_state >= self.states {
+            return;
+        }
+        match symbol {
+            Some(range) => {
+                self.alphabet.insert(range.start);
+                if let Some(after_end) = next_char(range.end) {
+                    self.alphabet.insert(after_end);
+                }
+            }
+            None => return self.add_epsilon_transition(from_state, to_state, None),
         }
-        self.add_from_transition(from_state, to_state, atom);
-        self.add_to_transition(from_state, to_state, atom);
+        self.add_from_transition(from_state, to_state, symbol);
+        self.add_to_transition(from_state, to_state, symbol);
         self.kind = AutomatonKind::Nfa;
     }
 
@@ -227,61 +854,509 @@ impl Automaton {
         }
     }
 
+    /// Returns a dfa matching every string matched by both `self` and `other`,
+    /// which is the product of the dfas of both
     pub fn intersection(&self, other: &Automaton) -> Automaton {
-        // For each pair of states,
-        let mut mul_dfa = Automaton::new();
-        let mul_alphabet: BTreeSet<char> = self.alphabet.union(&other.alphabet).cloned().collect();
-        let mut pair_to_dfa: HashMap<(u32, u32), u32> = HashMap::new();
-        for self_from_state in 0..self.states {
-            for other_from_state in 0..other.states {
-                let mul_from_state = match pair_to_dfa.get(&(self_from_state, other_from_state)) {
-                    Some(mul_state) => *mul_state,
-                    None => {
-                        let mul_from_state = mul_dfa.add_state();
-                        pair_to_dfa.insert((self_from_state, other_from_state), mul_from_state);
-                        mul_dfa.set_accepting(
-                            mul_from_state,
-                            self.accepting_states.contains(&self_from_state)
-                                && other.accepting_states.contains(&other_from_state),
-                        );
-                        mul_from_state
+        self.as_dfa()
+            .product(&other.as_dfa(), |self_accepts, other_accepts| {
+                self_accepts && other_accepts
+            })
+    }
+
+    /// Returns an automaton matching every string matched by `self` or by `other`.
+    ///
+    /// The union is an nfa with a new start state and epsilon transitions to the start states of both,
+    /// where the capture groups of `other` are numbered after the ones of `self`, and which has the [`MatchKind`] of `self`.
+    /// Like in [`Automaton::concat`], only the capture groups of nfas are kept.
+    ///
+    /// A dfa with assertions like ```\b``` can not be part of an nfa, so if either is one,
+    /// the union is the product of the dfas of both instead, which is a dfa.
+    pub fn union(&self, other: &Automaton) -> Automaton {
+        if self.looks.is_some() || other.looks.is_some() {
+            return self
+                .as_dfa()
+                .product(&other.as_dfa(), |self_accepts, other_accepts| {
+                    self_accepts || other_accepts
+                });
+        }
+        let first = self.renumbered_operand(0);
+        let second = other.renumbered_operand(first.capture_groups());
+
+        let mut union_automaton = Automaton::new();
+        let union_start_state = union_automaton.add_state();
+        union_automaton.set_start_state(union_start_state);
+        let capture_names = merge_capture_names(&[&first, &second]);
+        for automaton in [first, second] {
+            let union_offset = union_automaton.states;
+            let start_state = automaton.start_state;
+            let accepting_states = automaton.accepting_states.clone();
+            union_automaton.add_states_and_transitions(automaton);
+            union_automaton.add_transition(union_start_state, start_state + union_offset, None);
+            for accepting_state in accepting_states {
+                union_automaton.set_accepting(accepting_state + union_offset, true);
+            }
+        }
+        union_automaton.capture_names = capture_names;
+        union_automaton.match_kind = self.match_kind;
+        union_automaton
+    }
+
+    /// Returns an nfa matching every string made of a string matched by `self` followed by a string matched by `other`.
+    ///
+    /// Like the other operations building an nfa from other automatons, it must be applied before
+    /// turning an automaton with assertions like ```\b``` into a dfa, since the dfa depends on the symbols around its input,
+    /// and fails otherwise. The capture groups of `other` are numbered after the ones of `self`, and only the capture groups
+    /// of nfas are kept, since a dfa does not know where they start and end. The nfa has the [`MatchKind`] of `self`.
+    pub fn concat(&self, other: &Automaton) -> Result<Automaton, LookError> {
+        let first = self.nfa_operand(0)?;
+        let second = other.nfa_operand(first.capture_groups())?;
+        let capture_names = merge_capture_names(&[&first, &second]);
+        let mut concat_automaton = translator::concatenate(vec![first, second]);
+        concat_automaton.capture_names = capture_names;
+        concat_automaton.match_kind = self.match_kind;
+        Ok(concat_automaton)
+    }
+
+    /// Returns an nfa matching any number of strings matched by `self`, one after the other, like ```a*```.
+    /// Like [`Automaton::concat`], it fails for a dfa with assertions like ```\b```.
+    pub fn star(&self) -> Result<Automaton, LookError> {
+        self.repeat(true, true)
+    }
+
+    /// Returns an nfa matching one or more strings matched by `self`, one after the other, like ```a+```.
+    /// Like [`Automaton::concat`], it fails for a dfa with assertions like ```\b```.
+    pub fn plus(&self) -> Result<Automaton, LookError> {
+        self.repeat(false, true)
+    }
+
+    /// Returns an nfa matching the empty string and every string matched by `self`, like ```a?```.
+    /// Like [`Automaton::concat`], it fails for a dfa with assertions like ```\b```.
+    pub fn optional(&self) -> Result<Automaton, LookError> {
+        self.repeat(true, false)
+    }
+
+    fn repeat(&self, optional: bool, repeating: bool) -> Result<Automaton, LookError> {
+        let inner = self.nfa_operand(0)?;
+        let capture_names = inner.capture_names.clone();
+        let mut repetition_automaton = translator::repeat(inner, optional, repeating, true);
+        repetition_automaton.capture_names = capture_names;
+        repetition_automaton.match_kind = self.match_kind;
+        Ok(repetition_automaton)
+    }
+
+    /// Returns a copy of `self` to build an nfa from, like [`Automaton::renumbered_operand`],
+    /// or fails if `self` is a dfa with a look table
+    fn nfa_operand(&self, groups_before: usize) -> Result<Automaton, LookError> {
+        if self.looks.is_some() {
+            return Err(LookError);
+        }
+        Ok(self.renumbered_operand(groups_before))
+    }
+
+    /// Returns a copy of `self` to build an nfa from, whose capture groups are numbered after `groups_before` other groups.
+    /// The capture groups of a dfa are dropped.
+    fn renumbered_operand(&self, groups_before: usize) -> Automaton {
+        let mut operand = self.clone();
+        if let AutomatonKind::Nfa = self.kind {
+            for epsilons in operand.ordered_epsilons.values_mut() {
+                for (_, tag) in epsilons {
+                    *tag = tag.map(|tag| tag + 2 * groups_before);
+                }
+            }
+        } else {
+            operand.capture_names.clear();
+        }
+        operand
+    }
+
+    /// Returns the number of capture groups, besides group 0 which is the whole match
+    fn capture_groups(&self) -> usize {
+        self.capture_names.len().saturating_sub(1)
+    }
+
+    /// Returns a dfa matching every string matched by `self` but not by `other`,
+    /// which is the intersection of `self` and the complement of `other`
+    pub fn difference(&self, other: &Automaton) -> Automaton {
+        self.as_dfa()
+            .product(&other.as_dfa(), |self_accepts, other_accepts| {
+                self_accepts && !other_accepts
+            })
+    }
+
+    /// Returns a dfa matching every string matched by exactly one of `self` and `other`
+    pub fn symmetric_difference(&self, other: &Automaton) -> Automaton {
+        self.as_dfa()
+            .product(&other.as_dfa(), |self_accepts, other_accepts| {
+                self_accepts != other_accepts
+            })
+    }
+
+    /// Returns `true` if the automaton does not match any string
+    pub fn is_empty(&self) -> bool {
+        self.as_dfa().shortest_match().is_none()
+    }
+
+    pub fn accepts_empty_string(&self) -> bool {
+        self.as_dfa().match_whole("")
+    }
+
+    /// Returns `true` if the automaton matches every string of symbols in `alphabet`
+    pub fn is_universal(&self, alphabet: &[CharRange]) -> bool {
+        self.complement_over(alphabet).is_empty()
+    }
+
+    /// Returns `true` if the automaton matches a finite number of strings.
+    ///
+    /// The dfa is trimmed to the states that can be reached from the start state and can reach an accepting state,
+    /// which matches an infinite number of strings only if it has a cycle.
+    pub fn is_finite(&self) -> bool {
+        let dfa = self.as_dfa();
+        let reachable =
+            dfa.reachable_states(std::iter::once(dfa.start_state), &dfa.from_transitions);
+        let co_reachable =
+            dfa.reachable_states(dfa.accepting_states.iter().copied(), &dfa.to_transitions);
+        let trimmed: BTreeSet<u32> = reachable.intersection(&co_reachable).copied().collect();
+
+        // A depth first search finds a cycle if it reaches a state that is still being visited
+        let mut visiting = BTreeSet::new();
+        let mut visited = BTreeSet::new();
+        for &root_state in &trimmed {
+            if visited.contains(&root_state) {
+                continue;
+            }
+            let mut unvisited = vec![(root_state, false)];
+            while let Some((state, leaving)) = unvisited.pop() {
+                if leaving {
+                    visiting.remove(&state);
+                    visited.insert(state);
+                    continue;
+                }
+                if visited.contains(&state) {
+                    continue;
+                }
+                visiting.insert(state);
+                unvisited.push((state, true));
+                for to_state in dfa
+                    .from_transitions
+                    .get(&state)
+                    .into_iter()
+                    .flat_map(|to_states| to_states.keys())
+                {
+                    if !trimmed.contains(to_state) || visited.contains(to_state) {
+                        continue;
                     }
-                };
-
-                for atom in &mul_alphabet {
-                    if let (Some(self_to_state), Some(other_to_state)) = (
-                        self.traverse_from(self_from_state, *atom),
-                        other.traverse_from(other_from_state, *atom),
-                    ) {
-                        // There is a transition from from_state to a to_state via atom for both self and other
-                        let mul_to_state = match pair_to_dfa.get(&(self_to_state, other_to_state)) {
-                            Some(mul_state) => *mul_state,
-                            None => {
-                                let mul_to_state = mul_dfa.add_state();
-                                pair_to_dfa.insert((self_to_state, other_to_state), mul_to_state);
-                                mul_dfa.set_accepting(
-                                    mul_to_state,
-                                    self.accepting_states.contains(&self_to_state)
-                                        && other.accepting_states.contains(&other_to_state),
-                                );
-                                mul_to_state
-                            }
-                        };
-
-                        // Add transition from mul_from_state to mul_to_state
-                        mul_dfa.add_transition(mul_from_state, mul_to_state, Some(*atom));
+                    if visiting.contains(to_state) {
+                        return false;
                     }
+                    unvisited.push((*to_state, false));
                 }
+            }
+        }
+        true
+    }
+
+    /// Returns every state that can be reached from `start_states` via `transitions`
+    fn reachable_states(
+        &self,
+        start_states: impl Iterator<Item = u32>,
+        transitions: &HashMap<u32, HashMap<u32, Symbols>>,
+    ) -> BTreeSet<u32> {
+        let mut reachable: BTreeSet<u32> = start_states.collect();
+        let mut to_visit: Vec<u32> = reachable.iter().copied().collect();
+        while let Some(from_state) = to_visit.pop() {
+            for to_state in transitions
+                .get(&from_state)
+                .into_iter()
+                .flat_map(|to_states| to_states.keys())
+            {
+                if reachable.insert(*to_state) {
+                    to_visit.push(*to_state);
+                }
+            }
+        }
+        reachable
+    }
+
+    /// Returns the number of strings of exactly `length` symbols matched by the automaton
+    pub fn count_strings_of_length(&self, length: usize) -> BigUint {
+        self.count_strings_by_length(length)
+            .pop()
+            .unwrap_or_default()
+    }
 
-                // Set start state in mul_dfa
-                if let Some(mul_start_state) =
-                    pair_to_dfa.get(&(self.start_state, other.start_state))
+    /// Returns the number of strings of at most `length` symbols matched by the automaton
+    pub fn count_strings_up_to_length(&self, length: usize) -> BigUint {
+        self.count_strings_by_length(length).into_iter().sum()
+    }
+
+    /// Returns the number of strings matched by the automaton, or `None` if it matches an infinite number of strings
+    pub fn count_strings(&self) -> Option<BigUint> {
+        let dfa = self.as_dfa();
+        if dfa.is_finite() {
+            // Without cycles, no string visits a state twice, so no string is longer than the number of states
+            Some(dfa.count_strings_up_to_length(dfa.states as usize))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the number of matched strings of every length from 0 to `max_length`.
+    ///
+    /// The number of paths of each length from the start state to every state of the dfa is computed from the number
+    /// of paths one symbol shorter, where a transition via a range of symbols counts as one path per symbol.
+    fn count_strings_by_length(&self, max_length: usize) -> Vec<BigUint> {
+        let dfa = self.as_dfa();
+        let mut counts = Vec::with_capacity(max_length + 1);
+        let mut paths: HashMap<u32, BigUint> = HashMap::new();
+        if dfa.start_state < dfa.states {
+            paths.insert(dfa.start_state, BigUint::from(1u32));
+        }
+        for length in 0..=max_length {
+            counts.push(
+                dfa.accepting_states
+                    .iter()
+                    .filter_map(|state| paths.get(state))
+                    .sum(),
+            );
+            if length == max_length {
+                break;
+            }
+
+            let mut next_paths: HashMap<u32, BigUint> = HashMap::new();
+            for (from_state, from_paths) in &paths {
+                for (to_state, symbols) in
+                    dfa.from_transitions.get(from_state).into_iter().flatten()
                 {
-                    mul_dfa.set_start_state(*mul_start_state);
+                    let symbols: u32 = symbols.iter().flatten().map(CharRange::len).sum();
+                    *next_paths.entry(*to_state).or_default() += from_paths * symbols;
                 }
             }
+            paths = next_paths;
+        }
+        counts
+    }
+
+    /// Checks whether `self` and `other` match exactly the same strings.
+    ///
+    /// # Return
+    /// `Ok(())` if they match the same strings.
+    ///
+    /// `Err` with the shortest string matched by only one of them, if they do not
+    pub fn equivalent(&self, other: &Automaton) -> Result<(), String> {
+        match self.symmetric_difference(other).shortest_match() {
+            Some(counterexample) => Err(counterexample),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks whether every string matched by `self` is also matched by `other`.
+    ///
+    /// # Return
+    /// `Ok(())` if every string matched by `self` is matched by `other`.
+    ///
+    /// `Err` with the shortest string matched by `self` but not by `other`, if there is one
+    pub fn is_subset_of(&self, other: &Automaton) -> Result<(), String> {
+        match self.difference(other).shortest_match() {
+            Some(witness) => Err(witness),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the shortest string matched by the dfa, if it matches any, by a breadth first search from its start state.
+    /// Each symbol of the string is the first symbol of the range of symbols it stands in for.
+    fn shortest_match(&self) -> Option<String> {
+        let mut previous: HashMap<u32, Option<(u32, char)>> = HashMap::new();
+        let mut to_visit = VecDeque::new();
+        previous.insert(self.start_state, None);
+        to_visit.push_back(self.start_state);
+
+        while let Some(from_state) = to_visit.pop_front() {
+            if self.accepting_states.contains(&from_state) {
+                // Walk back to the start state to find the symbols leading here
+                let mut symbols = Vec::new();
+                let mut state = from_state;
+                while let Some(Some((previous_state, atom))) = previous.get(&state) {
+                    symbols.push(*atom);
+                    state = *previous_state;
+                }
+                return Some(symbols.into_iter().rev().collect());
+            }
+            for atom in &self.alphabet {
+                if let Some(to_state) = self.traverse_from(from_state, *atom) {
+                    if let Entry::Vacant(entry) = previous.entry(to_state) {
+                        entry.insert(Some((from_state, *atom)));
+                        to_visit.push_back(to_state);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Returns a dfa matching every string that is not matched by `self`, where any symbol can be read
+    pub fn complement(&self) -> Automaton {
+        self.complement_over(&[CharRange::new('\0', char::MAX)])
+    }
+
+    /// Returns a dfa matching every string of symbols in `alphabet` that is not matched by `self`.
+    ///
+    /// The dfa of `self` is completed by sending every missing transition via `alphabet` to the dead state,
+    /// which then becomes the only accepting state that was not accepting before,
+    /// and every transition via a symbol outside of `alphabet` is removed.
+    pub fn complement_over(&self, alphabet: &[CharRange]) -> Automaton {
+        let dfa = self.as_dfa();
+        let mut complement_dfa = Automaton::new();
+        complement_dfa.add_states(dfa.states + 1);
+        let dead_state = dfa.states;
+
+        let mut complement_alphabet = dfa.alphabet.clone();
+        complement_alphabet.insert('\0');
+        for range in alphabet {
+            complement_alphabet.insert(range.start);
+            complement_alphabet.extend(next_char(range.end));
+        }
+
+        for from_state in 0..=dead_state {
+            for atom in &complement_alphabet {
+                if !alphabet.iter().any(|range| range.contains(*atom)) {
+                    continue;
+                }
+                let to_state = dfa.traverse_from(from_state, *atom).unwrap_or(dead_state);
+                complement_dfa.add_range_transition(
+                    from_state,
+                    to_state,
+                    symbol_range(&complement_alphabet, *atom),
+                );
+            }
+            complement_dfa.set_accepting(from_state, !dfa.accepting_states.contains(&from_state));
+        }
+
+        complement_dfa.set_start_state(dfa.start_state);
+        if dfa.looks.is_some() {
+            let accepting_before = (0..=dead_state)
+                .filter_map(|state| {
+                    let mut contexts: BTreeSet<LookContext> = LookContext::ALL
+                        .iter()
+                        .copied()
+                        .filter(|context| !dfa.accepting_contexts(state).contains(context))
+                        .collect();
+                    contexts.remove(&LookContext::TextBoundary);
+                    if contexts.is_empty() {
+                        None
+                    } else {
+                        Some((state, contexts))
+                    }
+                })
+                .collect();
+            complement_dfa.looks = Some(LookTable {
+                // The dead state of the dfa is a state of the complement
+                start_states: LookContext::ALL
+                    .iter()
+                    .map(|&behind| (behind, dfa.start_state_after(behind)))
+                    .collect(),
+                accepting_before,
+            });
+        }
+        complement_dfa.kind = AutomatonKind::Dfa;
+        complement_dfa
+    }
+
+    /// Returns `self` if it is a dfa, otherwise the dfa built from it
+    fn as_dfa(&self) -> Cow<'_, Automaton> {
+        match self.kind {
+            AutomatonKind::Nfa => Cow::Owned(self.clone().into_dfa()),
+            _ => Cow::Borrowed(self),
+        }
+    }
+
+    /// Returns the product of the dfas `self` and `other`, which simulates both at the same time.
+    /// A state of the product is accepting if `accepting` returns `true` given whether each dfa accepts.
+    ///
+    /// Unlike in [`Automaton::intersection`], either dfa may be in its dead state,
+    /// which is never accepting. The product is in its own dead state when both are.
+    fn product(&self, other: &Automaton, accepting: impl Fn(bool, bool) -> bool) -> Automaton {
+        let mut product_dfa = Automaton::new();
+        let product_alphabet: BTreeSet<char> =
+            self.alphabet.union(&other.alphabet).cloned().collect();
+        let mut pairs: Worklist<ProductPair, u32> = Worklist::new();
+        let has_looks = self.looks.is_some() || other.looks.is_some();
+        let behind_contexts = LookContext::behind_contexts(has_looks);
+        let mut start_states = BTreeMap::new();
+        for &behind in behind_contexts {
+            let pair = (
+                Some(self.start_state_after(behind)),
+                Some(other.start_state_after(behind)),
+            );
+            let product_state = pairs.state_for(pair, || product_dfa.add_state());
+            start_states.insert(behind, product_state);
+        }
+
+        while let Some(((self_from_state, other_from_state), product_from_state)) = pairs.next() {
+            for atom in &product_alphabet {
+                let pair = (
+                    self_from_state.and_then(|state| self.traverse_from(state, *atom)),
+                    other_from_state.and_then(|state| other.traverse_from(state, *atom)),
+                );
+                if pair == (None, None) {
+                    continue;
+                }
+                let product_to_state = pairs.state_for(pair, || product_dfa.add_state());
+                product_dfa.add_range_transition(
+                    product_from_state,
+                    product_to_state,
+                    symbol_range(&product_alphabet, *atom),
+                );
+            }
+        }
+
+        let mut accepting_before = BTreeMap::new();
+        for (&(self_state, other_state), &product_state) in pairs.states() {
+            let self_contexts = self_state
+                .map(|state| self.accepting_contexts(state))
+                .unwrap_or_default();
+            let other_contexts = other_state
+                .map(|state| other.accepting_contexts(state))
+                .unwrap_or_default();
+            let mut contexts: BTreeSet<LookContext> = behind_contexts
+                .iter()
+                .copied()
+                .filter(|context| {
+                    accepting(
+                        self_contexts.contains(context),
+                        other_contexts.contains(context),
+                    )
+                })
+                .collect();
+            product_dfa.set_accepting(product_state, contexts.remove(&LookContext::TextBoundary));
+            if !contexts.is_empty() {
+                accepting_before.insert(product_state, contexts);
+            }
+        }
+
+        if let Some(start_state) = start_states.get(&LookContext::TextBoundary) {
+            product_dfa.set_start_state(*start_state);
+        }
+        if has_looks {
+            product_dfa.looks = Some(LookTable {
+                start_states,
+                accepting_before,
+            });
+        }
+        product_dfa.kind = AutomatonKind::Dfa;
+        product_dfa
+    }
+
+    /// Returns the state to start from when the symbol before the input is described by `behind`
+    fn start_state_after(&self, behind: LookContext) -> u32 {
+        match &self.looks {
+            // A start state that is missing from the look table is the dead state
+            Some(looks) => looks
+                .start_states
+                .get(&behind)
+                .copied()
+                .unwrap_or(self.states),
+            None => self.start_state,
         }
-        mul_dfa
     }
 
     pub fn to_dot_format(&self) -> String {
@@ -311,9 +1386,8 @@ impl Automaton {
                                 "{} -> {} [label=\"{}\"];",
                                 from_state,
                                 to_state,
-                                symbols
+                                merge_ranges(symbols.iter().filter_map(|s| *s))
                                     .iter()
-                                    .filter_map(|s| *s)
                                     .map(|s| s.to_string())
                                     .collect::<Vec<String>>()
                                     .join(", ")
@@ -321,93 +1395,92 @@ impl Automaton {
                         })
                     }),
             )
+            .chain(
+                self.look_transitions
+                    .iter()
+                    .flat_map(|(from_state, to_states)| {
+                        to_states.iter().map(move |(to_state, look)| {
+                            format!(
+                                "{} -> {} [label=\"{:?}\" style=dashed];",
+                                from_state, to_state, look
+                            )
+                        })
+                    }),
+            )
             .chain(std::iter::once("}".into()))
             .collect::<Vec<String>>();
         lines.join("\n")
     }
 
-    fn get_equivalent_states(&self) -> HashMap<u32, BTreeSet<u32>> {
-        let marked_states = self.get_marked_states_table();
-        let mut equivalent_composite_states = HashMap::new();
-        for s1 in 0..self.states {
-            let mut equivalent_to_s1 = BTreeSet::new();
-            for s2 in 0..self.states {
-                if !marked_states[s1 as usize][s2 as usize] {
-                    equivalent_to_s1.insert(s2);
-                }
+    /// Returns the class of equivalent states of every state and of the dead state, which is the last one.
+    ///
+    /// States start out in the same class if they are accepting before the same symbols, since the dead state
+    /// is never accepting. Then, states are split into new classes until every state in a class transitions
+    /// to the same classes via every symbol, which takes at most one pass per state.
+    fn equivalence_classes(&self) -> Vec<u32> {
+        let dead_state = self.states;
+        let mut classes = Vec::with_capacity(dead_state as usize + 1);
+        let mut class_ids = HashMap::new();
+        for state in 0..=dead_state {
+            let next_id = class_ids.len() as u32;
+            classes.push(
+                *class_ids
+                    .entry(self.accepting_contexts(state))
+                    .or_insert(next_id),
+            );
+        }
+
+        let mut class_count = class_ids.len();
+        loop {
+            let mut class_ids = HashMap::new();
+            let split_classes: Vec<u32> = (0..=dead_state)
+                .map(|state| {
+                    let to_classes = self.class_transitions(state, &classes);
+                    let next_id = class_ids.len() as u32;
+                    *class_ids
+                        .entry((classes[state as usize], to_classes))
+                        .or_insert(next_id)
+                })
+                .collect();
+            classes = split_classes;
+            if class_ids.len() == class_count {
+                return classes;
             }
-            equivalent_composite_states.insert(s1, equivalent_to_s1);
+            class_count = class_ids.len();
         }
-        equivalent_composite_states
     }
 
-    fn get_marked_states_table(&self) -> Vec<Vec<bool>> {
-        let dead_state = self.states;
-        let mut marked_states_table: Vec<Vec<bool>> =
-            vec![vec![false; self.states as usize + 1]; self.states as usize + 1];
-        for accepting_state in &self.accepting_states {
-            // Dead state is never accepting
-            marked_states_table[*accepting_state as usize][dead_state as usize] = true;
-            marked_states_table[dead_state as usize][*accepting_state as usize] = true;
-
-            for non_accepting_state in
-                (0..self.states).filter(|e| !self.accepting_states.contains(e))
-            {
-                marked_states_table[non_accepting_state as usize][*accepting_state as usize] = true;
-                marked_states_table[*accepting_state as usize][non_accepting_state as usize] = true;
-            }
-        }
-        let mut marked_a_pair = true;
-        while marked_a_pair {
-            marked_a_pair = false;
-
-            // Choose a pair of states
-            'mark: for s1 in 0..=self.states {
-                for s2 in 0..s1 {
-                    if !marked_states_table[s1 as usize][s2 as usize] {
-                        // Check if there is any transition from (s1, s2) to a marked pair
-                        for c in &self.alphabet {
-                            match (self.traverse_from(s1, *c), self.traverse_from(s2, *c)) {
-                                (None, Some(s2_to_state)) => {
-                                    // s1 transitions to dead state, there is a transition for s2
-                                    if marked_states_table[dead_state as usize]
-                                        [s2_to_state as usize]
-                                    {
-                                        marked_states_table[s1 as usize][s2 as usize] = true;
-                                        marked_states_table[s2 as usize][s1 as usize] = true;
-                                        marked_a_pair = true;
-                                        break 'mark;
-                                    }
-                                }
-                                (Some(s1_to_state), None) => {
-                                    // s2 transitions to dead state, there is a transition for s1
-                                    if marked_states_table[dead_state as usize]
-                                        [s1_to_state as usize]
-                                    {
-                                        marked_states_table[s1 as usize][s2 as usize] = true;
-                                        marked_states_table[s2 as usize][s1 as usize] = true;
-                                        marked_a_pair = true;
-                                        break 'mark;
-                                    }
-                                }
-                                (Some(s1_to_state), Some(s2_to_state)) => {
-                                    if marked_states_table[s1_to_state as usize]
-                                        [s2_to_state as usize]
-                                    {
-                                        marked_states_table[s1 as usize][s2 as usize] = true;
-                                        marked_states_table[s2 as usize][s1 as usize] = true;
-                                        marked_a_pair = true;
-                                        break 'mark;
-                                    }
-                                }
-                                (None, None) => {}
-                            }
-                        }
-                    }
+    /// Returns the ranges of symbols that lead from `state` out of the class of the dead state,
+    /// sorted and with adjacent ranges merged if they lead to the same class in `classes`
+    fn class_transitions(&self, state: u32, classes: &[u32]) -> Vec<(CharRange, u32)> {
+        let dead_class = classes[self.states as usize];
+        let mut ranges: Vec<(CharRange, u32)> = self
+            .from_transitions
+            .get(&state)
+            .into_iter()
+            .flatten()
+            .filter(|(to_state, _)| classes[**to_state as usize] != dead_class)
+            .flat_map(|(to_state, symbols)| {
+                symbols
+                    .iter()
+                    .flatten()
+                    .map(move |range| (*range, classes[*to_state as usize]))
+            })
+            .collect();
+        ranges.sort_unstable();
+
+        let mut merged_ranges: Vec<(CharRange, u32)> = Vec::with_capacity(ranges.len());
+        for (range, class) in ranges {
+            match merged_ranges.last_mut() {
+                Some((last, last_class))
+                    if *last_class == class && next_char(last.end) == Some(range.start) =>
+                {
+                    last.end = range.end;
                 }
+                _ => merged_ranges.push((range, class)),
             }
         }
-        marked_states_table
+        merged_ranges
     }
 
     /// Returns the set of states that can be reached from a given starting state
@@ -435,31 +1508,75 @@ impl Automaton {
     }
 
     /// Returns the set of states that can be reached from a given composite state
-    /// by reading one given atom
-    ///
-    /// # Arguments
-    ///
-    /// * `from_state_set` - The composite state from which traversal begins
-    /// * `atom` - The atom via which we are allowed to traverse
-    fn atom_closure(&self, from_state_set: &BTreeSet<u32>, atom: char) -> BTreeSet<u32> {
-        let mut atom_closure = BTreeSet::new();
-        for from_state in from_state_set {
-            if let Some(from_transitions) = self.from_transitions.get(&from_state) {
-                for (to_state, atoms_set) in from_transitions {
-                    if atoms_set.contains(&Some(atom)) {
-                        atom_closure = atom_closure
-                            .union(&self.epsilon_closure(*to_state))
-                            .cloned()
-                            .collect();
+    /// without reading any input, traversing epsilon-transitions and the look transitions
+    /// whose assertion holds between a symbol described by `behind` and a symbol described by `ahead`
+    fn look_closure(
+        &self,
+        from_state_set: &BTreeSet<u32>,
+        behind: LookContext,
+        ahead: LookContext,
+    ) -> BTreeSet<u32> {
+        let mut reachable_states = BTreeSet::new();
+        let mut unvisited_states: VecDeque<u32> = from_state_set.iter().copied().collect();
+        while let Some(unvisited_state) = unvisited_states.pop_front() {
+            if !reachable_states.insert(unvisited_state) {
+                continue;
+            }
+            for to_state in self.epsilon_closure(unvisited_state) {
+                if !reachable_states.contains(&to_state) {
+                    unvisited_states.push_back(to_state);
+                }
+            }
+            if let Some(look_transitions) = self.look_transitions.get(&unvisited_state) {
+                for (to_state, look) in look_transitions {
+                    if look.holds(behind, ahead) && !reachable_states.contains(to_state) {
+                        unvisited_states.push_back(*to_state);
                     }
                 }
             }
         }
 
-        atom_closure
+        reachable_states
     }
 
-    fn add_from_transition(&mut self, from_state: u32, to_state: u32, atom: Option<char>) {
+    /// Pairs `state_set` with what was read before reaching it, unless none of its states have look transitions
+    fn look_state(&self, state_set: BTreeSet<u32>, behind: LookContext) -> LookState {
+        if state_set
+            .iter()
+            .any(|state| self.look_transitions.contains_key(state))
+        {
+            (state_set, behind)
+        } else {
+            (state_set, LookContext::TextBoundary)
+        }
+    }
+
+    /// Returns the sets of states that can be reached from a given composite state
+    /// by reading one atom, for every range of atoms that reach the same states, sorted by range
+    fn range_closures(&self, from_state_set: &BTreeSet<u32>) -> Vec<(CharRange, BTreeSet<u32>)> {
+        let transitions = from_state_set
+            .iter()
+            .filter_map(|from_state| self.from_transitions.get(from_state))
+            .flatten()
+            .flat_map(|(to_state, atoms_set)| {
+                atoms_set
+                    .iter()
+                    .flatten()
+                    .map(move |range| (*range, *to_state))
+            });
+        split_ranges(transitions)
+            .into_iter()
+            .map(|(range, to_states)| {
+                let closure = to_states
+                    .iter()
+                    .flat_map(|to_state| self.epsilon_closure(*to_state))
+                    .collect();
+                (range, closure)
+            })
+            .collect()
+    }
+
+    fn add_from_transition(&mut self, from_state: u32, to_state: u32, atom: Option<CharRange>) {
         match self.from_transitions.get_mut(&from_state) {
             Some(to_states) => {
                 // There is some transition from from_state to some other state
@@ -484,7 +1601,7 @@ impl Automaton {
         }
     }
 
-    fn add_to_transition(&mut self, from_state: u32, to_state: u32, atom: Option<char>) {
+    fn add_to_transition(&mut self, from_state: u32, to_state: u32, atom: Option<CharRange>) {
         match self.to_transitions.get_mut(&to_state) {
             Some(from_states) => {
                 // There is some transition from some other state to to_state
@@ -509,6 +1626,43 @@ impl Automaton {
         }
     }
 
+    /// Returns the alphabet refined so that every [`Look`] of the look transitions
+    /// holds in the same way for all symbols that stand in for each other
+    pub(crate) fn look_alphabet(&self) -> BTreeSet<char> {
+        let mut alphabet = self.alphabet.clone();
+        let looks: BTreeSet<Look> = self
+            .look_transitions
+            .values()
+            .flatten()
+            .map(|(_, look)| *look)
+            .collect();
+        for look in looks {
+            alphabet.extend(look.boundaries());
+        }
+        alphabet
+    }
+
+    /// Returns the ranges of symbols that are of the same kind, sorted and covering every symbol,
+    /// so that every [`Look`] of the look transitions holds in the same way for all symbols of a range,
+    /// or no ranges if there are no look transitions
+    pub(crate) fn context_ranges(&self) -> Vec<(CharRange, LookContext)> {
+        if self.look_transitions.is_empty() {
+            return Vec::new();
+        }
+        // Every range of symbols between two symbols of the alphabet is of the same kind
+        let alphabet = self.look_alphabet();
+        let mut context_ranges: Vec<(CharRange, LookContext)> = Vec::new();
+        for c in &alphabet {
+            let range = symbol_range(&alphabet, *c);
+            let context = LookContext::of(Some(*c));
+            match context_ranges.last_mut() {
+                Some((last, last_context)) if *last_context == context => last.end = range.end,
+                _ => context_ranges.push((range, context)),
+            }
+        }
+        context_ranges
+    }
+
     fn add_states(&mut self, states: u32) {
         self.states += states;
     }
@@ -520,105 +1674,392 @@ impl Default for Automaton {
     }
 }
 
-fn nfa_to_dfa(automaton: &Automaton) -> Automaton {
+fn nfa_to_dfa(automaton: &Automaton, budget: &mut Budget) -> Result<Automaton, LimitError> {
     let mut minimized_dfa = Automaton::new();
-    let mut comp_to_dfa = HashMap::new();
+    let mut comps: Worklist<LookState, u32> = Worklist::new();
 
-    let mut visited_comp = BTreeSet::new();
-    let mut to_visit_comp = VecDeque::new();
+    let has_looks = !automaton.look_transitions.is_empty();
+    let behind_contexts = LookContext::behind_contexts(has_looks);
+    let context_ranges = automaton.context_ranges();
+    let ahead_contexts: Vec<LookContext> = if has_looks {
+        context_ranges
+            .iter()
+            .map(|(_, context)| *context)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    } else {
+        vec![LookContext::TextBoundary]
+    };
 
     let comp_start_state = automaton.epsilon_closure(automaton.start_state);
+    let mut start_states = BTreeMap::new();
+    for &behind in behind_contexts {
+        let comp = automaton.look_state(comp_start_state.clone(), behind);
+        let dfa_id = comps.try_state_for(comp, |comp| {
+            // Every composite state is stored both as a key and in the states left to visit
+            budget.add_state(2 * comp.0.len())?;
+            Ok(minimized_dfa.add_state())
+        })?;
+        start_states.insert(behind, dfa_id);
+    }
 
-    to_visit_comp.push_back(comp_start_state.clone());
-    while let Some(from_comp) = to_visit_comp.pop_front() {
-        visited_comp.insert(from_comp.clone());
-        let from_dfa_id = match comp_to_dfa.get(&from_comp) {
-            Some(dfa_id) => *dfa_id,
-            None => {
-                let dfa_id = minimized_dfa.add_state();
-                comp_to_dfa.insert(from_comp.clone(), dfa_id);
-                minimized_dfa.set_accepting(
-                    dfa_id,
-                    from_comp
-                        .iter()
-                        .any(|s| automaton.accepting_states.contains(&s)), // state is accepting if any of the states in to_comp is accepting
-                );
-                dfa_id
-            }
-        };
-        for c in &automaton.alphabet {
-            let to_comp = automaton.atom_closure(&from_comp, *c);
-            if !to_comp.is_empty() {
-                if let Some(to_dfa_id) = comp_to_dfa.get(&to_comp) {
-                    // Composite state is already in the minimized dfa
-                    minimized_dfa.add_transition(from_dfa_id, *to_dfa_id, Some(*c));
-                } else {
-                    // Composite state is not in the minimized dfa
-                    let to_dfa_id = minimized_dfa.add_state();
-                    minimized_dfa.set_accepting(
-                        to_dfa_id,
-                        to_comp
-                            .iter()
-                            .any(|s| automaton.accepting_states.contains(&s)), // state is accepting if any of the states in to_comp is accepting
-                    );
-                    to_visit_comp.push_back(to_comp.clone());
-                    comp_to_dfa.insert(to_comp, to_dfa_id);
-                    minimized_dfa.add_transition(from_dfa_id, to_dfa_id, Some(*c));
+    while let Some(((from_comp, behind), from_dfa_id)) = comps.next() {
+        // The states that can be left via a symbol depend on what kind of symbol it is
+        let mut to_comps = Vec::new();
+        for &ahead in &ahead_contexts {
+            let resolved_comp = automaton.look_closure(&from_comp, behind, ahead);
+            for (range, to_comp) in automaton.range_closures(&resolved_comp) {
+                if !has_looks {
+                    to_comps.push((range, (to_comp, LookContext::TextBoundary)));
+                    continue;
+                }
+                for ahead_range in context_ranges_within(&context_ranges, range, ahead) {
+                    to_comps.push((ahead_range, automaton.look_state(to_comp.clone(), ahead)));
                 }
             }
         }
+        to_comps.sort_unstable_by_key(|(range, _)| *range);
+
+        for (range, to_comp) in to_comps {
+            let to_dfa_id = comps.try_state_for(to_comp, |comp| {
+                budget.add_state(2 * comp.0.len())?;
+                Ok(minimized_dfa.add_state())
+            })?;
+            budget.add_transition()?;
+            minimized_dfa.add_range_transition(from_dfa_id, to_dfa_id, range);
+        }
     }
 
-    minimized_dfa.set_start_state(comp_to_dfa[&comp_start_state]);
+    // A state is accepting if any of the states in its composite state is accepting
+    let mut accepting_before = BTreeMap::new();
+    for ((comp, behind), dfa_id) in comps.states() {
+        let mut contexts: BTreeSet<LookContext> = behind_contexts
+            .iter()
+            .copied()
+            .filter(|&ahead| {
+                automaton
+                    .look_closure(comp, *behind, ahead)
+                    .iter()
+                    .any(|s| automaton.accepting_states.contains(s))
+            })
+            .collect();
+        minimized_dfa.set_accepting(*dfa_id, contexts.remove(&LookContext::TextBoundary));
+        if !contexts.is_empty() {
+            accepting_before.insert(*dfa_id, contexts);
+        }
+    }
+
+    if let Some(start_state) = start_states.get(&LookContext::TextBoundary) {
+        minimized_dfa.set_start_state(*start_state);
+    }
+    if has_looks {
+        minimized_dfa.looks = Some(LookTable {
+            start_states,
+            accepting_before,
+        });
+    }
+    // The tagged dfas are limited on their own, so that the limits of the dfa do not depend on them
+    if !automaton.capture_names.is_empty() {
+        minimized_dfa.tagged = Some(Box::new(LazyTaggedDfa {
+            nfa: Box::new(automaton.clone()),
+            limits: budget.limits.clone(),
+            tagged: OnceLock::new(),
+        }));
+        minimized_dfa.capture_names = automaton.capture_names.clone();
+    }
+    if automaton.match_kind == MatchKind::LeftmostFirst {
+        let prioritized = TaggedDfa::new(automaton, true, &mut Budget::new(budget.limits))?;
+        minimized_dfa.prioritized = Some(Box::new(prioritized));
+    }
+    minimized_dfa.match_kind = automaton.match_kind;
     minimized_dfa.kind = AutomatonKind::Dfa;
-    minimized_dfa
+    Ok(minimized_dfa)
 }
 
-fn dfa_to_minimized_dfa(automaton: &Automaton) -> Automaton {
-    let equivalent_states = automaton.get_equivalent_states();
+/// Returns the parts of `range` that are made up of symbols of the kind `context`,
+/// where `context_ranges` are sorted and cover every symbol of `range`
+pub(crate) fn context_ranges_within(
+    context_ranges: &[(CharRange, LookContext)],
+    range: CharRange,
+    context: LookContext,
+) -> impl Iterator<Item = CharRange> + '_ {
+    let first =
+        context_ranges.partition_point(|(context_range, _)| context_range.end < range.start);
+    context_ranges[first..]
+        .iter()
+        .take_while(move |(context_range, _)| context_range.start <= range.end)
+        .filter(move |(_, range_context)| *range_context == context)
+        .map(move |(context_range, _)| {
+            CharRange::new(
+                context_range.start.max(range.start),
+                context_range.end.min(range.end),
+            )
+        })
+}
+
+/// Returns the names of the capture groups of every one of `operands`, one after the other after group 0,
+/// or no names if none of them has capture groups
+fn merge_capture_names(operands: &[&Automaton]) -> Vec<Option<String>> {
+    if operands
+        .iter()
+        .all(|operand| operand.capture_names.is_empty())
+    {
+        return Vec::new();
+    }
+    std::iter::once(None)
+        .chain(
+            operands
+                .iter()
+                .flat_map(|operand| operand.capture_names.iter().skip(1).cloned()),
+        )
+        .collect()
+}
+
+/// The states of an automaton being built, each described by a key like the composite state it simulates,
+/// with the states that are yet to be visited in the order they were added
+pub(crate) struct Worklist<K, S> {
+    key_to_state: HashMap<K, S>,
+    to_visit: VecDeque<(K, S)>,
+}
+
+impl<K: Clone + Eq + Hash, S: Copy> Worklist<K, S> {
+    pub(crate) fn new() -> Self {
+        Worklist {
+            key_to_state: HashMap::new(),
+            to_visit: VecDeque::new(),
+        }
+    }
+
+    /// Returns the state described by `key`, adding it with `add_state` and to the states left to visit if it is new
+    pub(crate) fn try_state_for<E>(
+        &mut self,
+        key: K,
+        add_state: impl FnOnce(&K) -> Result<S, E>,
+    ) -> Result<S, E> {
+        if let Some(state) = self.key_to_state.get(&key) {
+            return Ok(*state);
+        }
+        let state = add_state(&key)?;
+        self.key_to_state.insert(key.clone(), state);
+        self.to_visit.push_back((key, state));
+        Ok(state)
+    }
+
+    /// Like [`Worklist::try_state_for`], for states that can always be added
+    fn state_for(&mut self, key: K, add_state: impl FnOnce() -> S) -> S {
+        match self.try_state_for(key, |_| Ok::<S, Infallible>(add_state())) {
+            Ok(state) => state,
+            Err(never) => match never {},
+        }
+    }
+
+    /// Returns the next state left to visit with its key
+    pub(crate) fn next(&mut self) -> Option<(K, S)> {
+        self.to_visit.pop_front()
+    }
+
+    /// Returns every state that was added with its key
+    fn states(&self) -> impl Iterator<Item = (&K, &S)> {
+        self.key_to_state.iter()
+    }
+}
+
+fn dfa_to_minimized_dfa(
+    automaton: &Automaton,
+    budget: &mut Budget,
+) -> Result<Automaton, LimitError> {
+    // Every pass of splitting the classes stores the ranges of symbols every state transitions by
+    let transition_ranges: usize = automaton
+        .from_transitions
+        .values()
+        .flat_map(|transitions| transitions.values())
+        .map(|symbols| symbols.len())
+        .sum();
+    let signatures_size = (automaton.states as usize + 1).saturating_add(transition_ranges);
+    budget.add_bytes(signatures_size.saturating_mul(std::mem::size_of::<(CharRange, u32)>()))?;
+    let classes = automaton.equivalence_classes();
+    let dead_class = classes[automaton.states as usize];
     let mut comp_state_to_dfa = HashMap::new();
+    let mut class_to_dfa = HashMap::new();
     let mut min_dfa = Automaton::new();
 
-    // First add all states to minimized dfa
-    for (s1, equivalent_to_s1) in &equivalent_states {
-        if let Some(dfa_state_id) = comp_state_to_dfa.get(s1) {
-            // s1 has already been added to min_dfa
-            let dfa_state_id = *dfa_state_id;
-            comp_state_to_dfa.insert(s1, dfa_state_id);
-        } else {
-            let dfa_state_id = min_dfa.add_state();
-            for equivalent_state in equivalent_to_s1 {
-                comp_state_to_dfa.insert(equivalent_state, dfa_state_id);
-            }
+    // First add a state for every class, except for the class of the dead state
+    let mut representatives = Vec::new();
+    for (state, class) in classes.iter().enumerate().take(automaton.states as usize) {
+        if *class == dead_class {
+            continue;
+        }
+        let state = state as u32;
+        let dfa_state_id = *class_to_dfa.entry(*class).or_insert_with(|| {
+            representatives.push(state);
+            min_dfa.add_state()
+        });
+        comp_state_to_dfa.insert(state, dfa_state_id);
+    }
+    if let Some(start_state) = comp_state_to_dfa.get(&automaton.start_state) {
+        min_dfa.set_start_state(*start_state);
+    }
 
-            if equivalent_to_s1.contains(&automaton.start_state) {
-                min_dfa.set_start_state(dfa_state_id);
-            }
+    // Then add the transitions of one state of every class, which are the same for all of them
+    for from_state in representatives {
+        let dfa_from_state = comp_state_to_dfa[&from_state];
+        min_dfa.set_accepting(
+            dfa_from_state,
+            automaton.accepting_states.contains(&from_state),
+        );
+        for (range, class) in automaton.class_transitions(from_state, &classes) {
+            min_dfa.add_range_transition(dfa_from_state, class_to_dfa[&class], range);
+        }
+    }
 
-            // Set accepting if all states in comp is accepting
-            min_dfa.set_accepting(
-                dfa_state_id,
-                equivalent_to_s1
-                    .iter()
-                    .all(|&state| automaton.accepting_states.contains(&state)),
-            );
+    if let Some(looks) = &automaton.looks {
+        min_dfa.looks = Some(LookTable {
+            start_states: looks
+                .start_states
+                .iter()
+                .filter_map(|(behind, state)| {
+                    comp_state_to_dfa
+                        .get(state)
+                        .map(|dfa_state| (*behind, *dfa_state))
+                })
+                .collect(),
+            accepting_before: looks
+                .accepting_before
+                .iter()
+                .filter_map(|(state, contexts)| {
+                    comp_state_to_dfa
+                        .get(state)
+                        .map(|dfa_state| (*dfa_state, contexts.clone()))
+                })
+                .collect(),
+        });
+    }
+    min_dfa.tagged = automaton.tagged.clone();
+    min_dfa.prioritized = automaton.prioritized.clone();
+    min_dfa.match_kind = automaton.match_kind;
+    min_dfa.capture_names = automaton.capture_names.clone();
+    min_dfa.kind = AutomatonKind::MinDfa;
+    Ok(min_dfa)
+}
+
+/// Returns the ranges of symbols matched by ```\w```, by its unicode or by its ascii definition
+fn word_ranges(unicode: bool) -> Vec<CharRange> {
+    if !unicode {
+        return vec![
+            CharRange::new('0', '9'),
+            CharRange::new('A', 'Z'),
+            CharRange::single('_'),
+            CharRange::new('a', 'z'),
+        ];
+    }
+    match regex_syntax::Parser::new()
+        .parse(r"\w")
+        .map(hir::Hir::into_kind)
+    {
+        Ok(HirKind::Class(hir::Class::Unicode(class))) => class
+            .iter()
+            .map(|range| CharRange::new(range.start(), range.end()))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Returns `true` if any of the ranges in `symbols` contains `atom`
+pub(crate) fn contains_atom(symbols: &Symbols, atom: char) -> bool {
+    symbols.iter().flatten().any(|range| range.contains(atom))
+}
+
+/// Splits the symbols read by `transitions` into ranges that reach the same targets,
+/// returning the targets of every such range, sorted by range
+pub(crate) fn split_ranges<T: Copy + Ord + Hash>(
+    transitions: impl IntoIterator<Item = (CharRange, T)>,
+) -> Vec<(CharRange, BTreeSet<T>)> {
+    // The targets that are reached only change where a range of some transition starts or ends
+    let mut boundaries: BTreeMap<char, (Vec<T>, Vec<T>)> = BTreeMap::new();
+    for (range, target) in transitions {
+        boundaries.entry(range.start).or_default().0.push(target);
+        if let Some(after_end) = next_char(range.end) {
+            boundaries.entry(after_end).or_default().1.push(target);
         }
     }
 
-    // Then add all transitions
-    for from_state in equivalent_states.keys() {
-        for c in &automaton.alphabet {
-            if let Some(to_state) = automaton.traverse_from(*from_state, *c) {
-                if let (Some(dfa_from_state), Some(dfa_to_state)) = (
-                    comp_state_to_dfa.get(&from_state),
-                    comp_state_to_dfa.get(&to_state),
-                ) {
-                    min_dfa.add_transition(*dfa_from_state, *dfa_to_state, Some(*c));
+    let mut split: Vec<(CharRange, BTreeSet<T>)> = Vec::new();
+    let mut target_counts: HashMap<T, usize> = HashMap::new();
+    let mut boundaries = boundaries.into_iter().peekable();
+    while let Some((start, (entered, left))) = boundaries.next() {
+        for target in left {
+            if let Entry::Occupied(mut count) = target_counts.entry(target) {
+                *count.get_mut() -= 1;
+                if *count.get() == 0 {
+                    count.remove();
                 }
             }
         }
+        for target in entered {
+            *target_counts.entry(target).or_default() += 1;
+        }
+        let end = match boundaries.peek() {
+            Some((next_start, _)) => previous_char(*next_start).unwrap_or(start),
+            None => char::MAX,
+        };
+        if target_counts.is_empty() {
+            continue;
+        }
+        let targets: BTreeSet<T> = target_counts.keys().copied().collect();
+        match split.last_mut() {
+            Some((last, last_targets))
+                if *last_targets == targets && next_char(last.end) == Some(start) =>
+            {
+                last.end = end;
+            }
+            _ => split.push((CharRange::new(start, end), targets)),
+        }
     }
+    split
+}
 
-    min_dfa.kind = AutomatonKind::MinDfa;
-    min_dfa
+/// Merges sorted `ranges` that overlap or are directly adjacent to each other
+fn merge_ranges(ranges: impl Iterator<Item = CharRange>) -> Vec<CharRange> {
+    let mut merged_ranges: Vec<CharRange> = Vec::new();
+    for range in ranges {
+        match merged_ranges.last_mut() {
+            Some(last) if next_char(last.end).map_or(true, |c| c >= range.start) => {
+                last.end = last.end.max(range.end);
+            }
+            _ => merged_ranges.push(range),
+        }
+    }
+    merged_ranges
+}
+
+/// Returns the range of symbols that `atom` stands in for in `alphabet`,
+/// from `atom` up to (but not including) the next symbol in `alphabet`
+fn symbol_range(alphabet: &BTreeSet<char>, atom: char) -> CharRange {
+    use std::ops::Bound::{Excluded, Unbounded};
+
+    let end = alphabet
+        .range((Excluded(atom), Unbounded))
+        .next()
+        .and_then(|&next_atom| previous_char(next_atom))
+        .unwrap_or(char::MAX);
+    CharRange::new(atom, end)
+}
+
+/// Returns the unicode scalar value directly after `c`, skipping the surrogate range
+fn next_char(c: char) -> Option<char> {
+    match c {
+        '\u{D7FF}' => Some('\u{E000}'),
+        char::MAX => None,
+        _ => std::char::from_u32(c as u32 + 1),
+    }
+}
+
+/// Returns the unicode scalar value directly before `c`, skipping the surrogate range
+fn previous_char(c: char) -> Option<char> {
+    match c {
+        '\u{E000}' => Some('\u{D7FF}'),
+        '\0' => None,
+        _ => std::char::from_u32(c as u32 - 1),
+    }
 }
@@ -0,0 +1,365 @@
+use crate::automaton::{
+    context_ranges_within, split_ranges, Automaton, Budget, CharRange, LimitError, LookContext,
+    Worklist,
+};
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Range;
+
+/// The spans of the capture groups in a string matched by an automaton
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Captures {
+    spans: Vec<Option<Range<usize>>>,
+    names: BTreeMap<String, usize>,
+}
+
+impl Captures {
+    /// Returns the captures of a match of the whole of `input` without any capture groups
+    pub(crate) fn whole(input: &str) -> Self {
+        Captures {
+            spans: vec![Some(0..input.len())],
+            names: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the span of capture group `index`, where group 0 is the whole match,
+    /// or `None` if the group did not take part in the match
+    pub fn get(&self, index: usize) -> Option<Range<usize>> {
+        self.spans.get(index).cloned().flatten()
+    }
+
+    /// Returns the span of the capture group named `name`, like ```(?P<name>a)```,
+    /// or `None` if there is no such group or it did not take part in the match
+    pub fn name(&self, name: &str) -> Option<Range<usize>> {
+        self.names.get(name).and_then(|&index| self.get(index))
+    }
+
+    /// Returns the number of capture groups, including group 0
+    pub fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+}
+
+/// A dfa that also finds the positions of the tags of an nfa,
+/// where tag 2n is the start and tag 2n+1 is the end of capture group n.
+///
+/// Every state of the dfa simulates an ordered list of nfa states (items), ordered by the priority of
+/// the paths leading to them, which is the order of the alternatives of an alternation and whether a repetition
+/// is greedy. Every item has its own row of registers, holding the last position of every tag on the path
+/// leading to it. Every transition tells how to compute each row of the next state from a row of the current state,
+/// so finding the captures of a string only takes a single pass over it.
+#[derive(Debug, Clone)]
+pub(crate) struct TaggedDfa {
+    tags: usize,
+    states: Vec<TaggedState>,
+    /// The state to start from, given the symbol right before the start of the input
+    start_states: BTreeMap<LookContext, usize>,
+    names: BTreeMap<String, usize>,
+}
+
+#[derive(Debug, Clone)]
+struct TaggedState {
+    /// The transitions via every range of symbols that leads somewhere, sorted by range,
+    /// with the operation computing each row of registers of the next state
+    transitions: Vec<(CharRange, usize, Vec<RegisterOperation>)>,
+    /// The operation computing the registers of the match if the input ends in this state
+    accepting: Option<RegisterOperation>,
+    /// The contexts of the next symbol for which this state is accepting,
+    /// where [`LookContext::TextBoundary`] means that the input ends
+    accepting_before: BTreeSet<LookContext>,
+}
+
+impl TaggedState {
+    /// Returns the state reached via `c` with the operations computing its registers, if any
+    fn transition(&self, c: char) -> Option<(&usize, &Vec<RegisterOperation>)> {
+        let index = self
+            .transitions
+            .partition_point(|(range, ..)| range.end < c);
+        match self.transitions.get(index) {
+            Some((range, to_state, operations)) if range.contains(c) => {
+                Some((to_state, operations))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Computes a row of registers by copying the row `origin`, then setting every tag in `set_tags` to the current position
+#[derive(Debug, Clone)]
+struct RegisterOperation {
+    origin: usize,
+    set_tags: Vec<usize>,
+}
+
+/// A state of a tagged dfa, composed of the nfa states it simulates in order of priority,
+/// and what was read right before reaching it
+type TaggedStateKey = (Vec<u32>, LookContext);
+
+impl TaggedDfa {
+    /// Builds the tagged dfa of `automaton`.
+    ///
+    /// If `prioritized` is set, every item of lower priority than an accepting item is dropped, since a match that
+    /// is preferred to anything they could match has been found. This makes the dfa find leftmost-first matches.
+    pub(crate) fn new(
+        automaton: &Automaton,
+        prioritized: bool,
+        budget: &mut Budget,
+    ) -> Result<TaggedDfa, LimitError> {
+        let tags = automaton
+            .ordered_epsilons
+            .values()
+            .flatten()
+            .filter_map(|(_, tag)| *tag)
+            .max()
+            .map_or(0, |tag| tag + 1)
+            .max(2 * automaton.capture_names.len());
+        let names = automaton
+            .capture_names
+            .iter()
+            .enumerate()
+            .filter_map(|(index, name)| name.as_ref().map(|name| (name.clone(), index)))
+            .collect();
+        let mut tagged_dfa = TaggedDfa {
+            tags,
+            states: Vec::new(),
+            start_states: BTreeMap::new(),
+            names,
+        };
+
+        let has_looks = !automaton.look_transitions.is_empty();
+        let behind_contexts = LookContext::behind_contexts(has_looks);
+        let context_ranges = automaton.context_ranges();
+        let mut keys: Worklist<TaggedStateKey, usize> = Worklist::new();
+        for &behind in behind_contexts {
+            let start_key = (vec![automaton.start_state], behind);
+            let start_state =
+                keys.try_state_for(start_key, |key| tagged_dfa.add_state(budget, key))?;
+            tagged_dfa.start_states.insert(behind, start_state);
+        }
+
+        while let Some(((items, behind), from_state)) = keys.next() {
+            let closures: Vec<(LookContext, Vec<(u32, RegisterOperation)>)> = behind_contexts
+                .iter()
+                .map(|&ahead| {
+                    let closure = tagged_closure(automaton, &items, behind, ahead, prioritized);
+                    (ahead, closure)
+                })
+                .collect();
+            for (ahead, closure) in &closures {
+                if let Some((_, operation)) = closure
+                    .iter()
+                    .find(|(state, _)| automaton.accepting_states.contains(state))
+                {
+                    let tagged_state = &mut tagged_dfa.states[from_state];
+                    if *ahead == LookContext::TextBoundary {
+                        tagged_state.accepting = Some(operation.clone());
+                    }
+                    tagged_state.accepting_before.insert(*ahead);
+                }
+            }
+            if !has_looks && tagged_dfa.states[from_state].accepting.is_some() {
+                tagged_dfa.states[from_state].accepting_before = LookContext::ALL.into();
+            }
+
+            let mut to_keys = Vec::new();
+            for (ahead, closure) in &closures {
+                // No symbol is of the kind of the end of the input
+                if has_looks && *ahead == LookContext::TextBoundary {
+                    continue;
+                }
+                // Step every item via each range, in order of priority, keeping only the first path to each nfa state
+                let transitions = closure.iter().enumerate().flat_map(|(item, (state, _))| {
+                    automaton
+                        .from_transitions
+                        .get(state)
+                        .into_iter()
+                        .flatten()
+                        .flat_map(move |(to_state, symbols)| {
+                            symbols
+                                .iter()
+                                .flatten()
+                                .map(move |range| (*range, (item, *to_state)))
+                        })
+                });
+                for (range, steps) in split_ranges(transitions) {
+                    let mut to_items = Vec::new();
+                    let mut operations = Vec::new();
+                    for (item, to_state) in steps {
+                        if !to_items.contains(&to_state) {
+                            to_items.push(to_state);
+                            operations.push(closure[item].1.clone());
+                        }
+                    }
+                    if !has_looks {
+                        to_keys.push((range, (to_items, LookContext::TextBoundary), operations));
+                        continue;
+                    }
+                    for ahead_range in context_ranges_within(&context_ranges, range, *ahead) {
+                        to_keys.push((ahead_range, (to_items.clone(), *ahead), operations.clone()));
+                    }
+                }
+            }
+            to_keys.sort_unstable_by_key(|(range, ..)| *range);
+
+            for (range, to_key, operations) in to_keys {
+                let to_state =
+                    keys.try_state_for(to_key, |key| tagged_dfa.add_state(budget, key))?;
+                budget.add_transition()?;
+                tagged_dfa.states[from_state]
+                    .transitions
+                    .push((range, to_state, operations));
+            }
+        }
+
+        Ok(tagged_dfa)
+    }
+
+    /// Adds the state described by `key`
+    fn add_state(
+        &mut self,
+        budget: &mut Budget,
+        key: &TaggedStateKey,
+    ) -> Result<usize, LimitError> {
+        // Every item is stored both in the key and in the states left to visit
+        budget.add_state(2 * key.0.len())?;
+        self.states.push(TaggedState {
+            transitions: Vec::new(),
+            accepting: None,
+            accepting_before: BTreeSet::new(),
+        });
+        Ok(self.states.len() - 1)
+    }
+
+    /// Returns the captures of `input` if the whole of it is matched
+    pub(crate) fn captures(&self, input: &str) -> Option<Captures> {
+        let mut state = self.start_state_after(LookContext::TextBoundary)?;
+        let mut registers = vec![vec![None; self.tags]];
+        let mut next_registers = Vec::new();
+        for (index, c) in input.char_indices() {
+            let (to_state, operations) = self.states.get(state)?.transition(c)?;
+            next_registers.clear();
+            next_registers.extend(
+                operations
+                    .iter()
+                    .map(|operation| self.apply(operation, &registers, index)),
+            );
+            std::mem::swap(&mut registers, &mut next_registers);
+            state = *to_state;
+        }
+
+        let operation = self.states.get(state)?.accepting.as_ref()?;
+        let tag_positions = self.apply(operation, &registers, input.len());
+        let mut spans: Vec<Option<Range<usize>>> = tag_positions
+            .chunks(2)
+            .map(|tag_pair| match tag_pair {
+                [Some(start), Some(end)] => Some(*start..*end),
+                _ => None,
+            })
+            .collect();
+        match spans.first_mut() {
+            Some(whole_match) => *whole_match = Some(0..input.len()),
+            None => spans.push(Some(0..input.len())),
+        }
+        Some(Captures {
+            spans,
+            names: self.names.clone(),
+        })
+    }
+
+    /// Returns the end of the match that begins at the byte offset `start` in `input`,
+    /// which is the leftmost-first match if the dfa is prioritized
+    pub(crate) fn match_prefix(&self, input: &str, start: usize) -> Option<usize> {
+        let behind = LookContext::of(input[..start].chars().next_back());
+        let mut state = self.start_state_after(behind)?;
+        let mut match_end = None;
+        for (index, c) in input[start..].char_indices() {
+            let tagged_state = self.states.get(state)?;
+            if tagged_state
+                .accepting_before
+                .contains(&LookContext::of(Some(c)))
+            {
+                match_end = Some(start + index);
+            }
+            match tagged_state.transition(c) {
+                Some((to_state, _)) => state = *to_state,
+                None => return match_end,
+            }
+        }
+        if self.states.get(state)?.accepting.is_some() {
+            match_end = Some(input.len());
+        }
+        match_end
+    }
+
+    fn start_state_after(&self, behind: LookContext) -> Option<usize> {
+        // Without look transitions, there is only a start state for the start of the input
+        self.start_states
+            .get(&behind)
+            .or_else(|| self.start_states.get(&LookContext::TextBoundary))
+            .copied()
+    }
+
+    fn apply(
+        &self,
+        operation: &RegisterOperation,
+        registers: &[Vec<Option<usize>>],
+        position: usize,
+    ) -> Vec<Option<usize>> {
+        let mut row = registers
+            .get(operation.origin)
+            .cloned()
+            .unwrap_or_else(|| vec![None; self.tags]);
+        for &tag in &operation.set_tags {
+            if let Some(register) = row.get_mut(tag) {
+                *register = Some(position);
+            }
+        }
+        row
+    }
+}
+
+/// Returns every nfa state that can be reached from `items` without reading any input, in order of priority,
+/// with the operation computing its registers from the row of the item it was reached from.
+///
+/// Look transitions are only traversed if they hold between a symbol described by `behind`
+/// and a symbol described by `ahead`. Only the first path to each state is kept.
+/// If `prioritized` is set, the closure ends at the first accepting state.
+fn tagged_closure(
+    automaton: &Automaton,
+    items: &[u32],
+    behind: LookContext,
+    ahead: LookContext,
+    prioritized: bool,
+) -> Vec<(u32, RegisterOperation)> {
+    let mut closure = Vec::new();
+    let mut visited = BTreeSet::new();
+    for (origin, item) in items.iter().enumerate() {
+        // Depth first, so that every path is explored before any path of lower priority
+        let mut unvisited = vec![(*item, Vec::new())];
+        while let Some((state, set_tags)) = unvisited.pop() {
+            if !visited.insert(state) {
+                continue;
+            }
+            let mut next: Vec<(u32, Vec<usize>)> = Vec::new();
+            for (to_state, tag) in automaton.ordered_epsilons.get(&state).into_iter().flatten() {
+                let mut to_set_tags = set_tags.clone();
+                to_set_tags.extend(tag);
+                next.push((*to_state, to_set_tags));
+            }
+            for (to_state, look) in automaton.look_transitions.get(&state).into_iter().flatten() {
+                if look.holds(behind, ahead) {
+                    next.push((*to_state, set_tags.clone()));
+                }
+            }
+            unvisited.extend(next.into_iter().rev());
+            closure.push((state, RegisterOperation { origin, set_tags }));
+            if prioritized && automaton.accepting_states.contains(&state) {
+                return closure;
+            }
+        }
+    }
+    closure
+}
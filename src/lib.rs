@@ -1,2 +1,3 @@
 pub mod automaton;
+pub mod captures;
 pub mod translator;
@@ -1,177 +1,764 @@
-use crate::automaton::Automaton;
-use regex_syntax::ast::parse::Parser;
+use crate::automaton::{Automaton, CharRange, Look, MatchKind};
+use regex_syntax::ast::parse::ParserBuilder;
 use regex_syntax::ast::{
-    Alternation, Ast, Class, ClassSet, ClassSetItem, ClassSetRange, Concat, Error, Repetition,
+    self, Ast, Class, ClassSet, ClassSetItem, ClassUnicodeKind, ClassUnicodeOpKind, Group,
+    GroupKind, RepetitionKind, RepetitionRange,
 };
-use std::collections::HashSet;
+use regex_syntax::hir::{self, ClassUnicode, ClassUnicodeRange, Hir, HirKind};
 
 type TranslatorResult = Result<Automaton, TranslatorError>;
 
-#[derive(Debug)]
-pub enum TranslatorError {
-    UnsupportedAst(Ast),
-    UnsupportedClass(Class),
-    UnsupportedClassSet(ClassSet),
-    UnsupportedClassSetItem(ClassSetItem),
-    ParserError(Error),
+/// An error that occurred when translating a regular expression,
+/// pointing at the part of the regular expression that caused it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TranslatorError {
+    kind: TranslatorErrorKind,
+    pattern: String,
+    span: Span,
 }
 
+/// The cause of a [`TranslatorError`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TranslatorErrorKind {
+    /// The regular expression uses a feature that can not be simulated by an automaton, like a backreference,
+    /// or that is not allowed where it is used, like ```\p{Greek}``` without the ```u``` flag
+    Unsupported(String),
+    /// The regular expression is not valid, described by the contained message
+    Syntax(String),
+    /// Groups, classes or repetitions are nested deeper than the contained nest limit
+    NestLimitExceeded(u32),
+    /// A counted repetition like ```a{3,8}``` repeats more times than the repetition limit allows
+    RepetitionLimitExceeded(u32),
+}
+
+/// The broad class of a [`TranslatorError`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCategory {
+    /// The regular expression uses a feature that can not be simulated by an automaton,
+    /// like a backreference, or that is not allowed where it is used, like ```\p{Greek}``` without the ```u``` flag
+    Unsupported,
+    /// The regular expression is not valid
+    Syntax,
+    /// The regular expression exceeds a configured limit, like the repetition limit
+    ResourceLimit,
+}
+
+/// The part of a regular expression that caused a [`TranslatorError`],
+/// from the byte offset `start` up to (but not including) the byte offset `end`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl From<ast::Span> for Span {
+    fn from(span: ast::Span) -> Self {
+        Self {
+            start: span.start.offset,
+            end: span.end.offset,
+        }
+    }
+}
+
+impl TranslatorError {
+    fn new(kind: TranslatorErrorKind, pattern: &str, span: ast::Span) -> Self {
+        Self {
+            kind,
+            pattern: pattern.to_string(),
+            span: span.into(),
+        }
+    }
+
+    pub fn kind(&self) -> &TranslatorErrorKind {
+        &self.kind
+    }
+
+    pub fn category(&self) -> ErrorCategory {
+        match &self.kind {
+            TranslatorErrorKind::Unsupported(_) => ErrorCategory::Unsupported,
+            TranslatorErrorKind::Syntax(_) => ErrorCategory::Syntax,
+            TranslatorErrorKind::NestLimitExceeded(_)
+            | TranslatorErrorKind::RepetitionLimitExceeded(_) => ErrorCategory::ResourceLimit,
+        }
+    }
+
+    /// The regular expression that could not be translated
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// The part of the regular expression that caused the error
+    pub fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl From<ast::Error> for TranslatorError {
+    fn from(err: ast::Error) -> Self {
+        let kind = match err.kind() {
+            ast::ErrorKind::UnsupportedBackreference | ast::ErrorKind::UnsupportedLookAround => {
+                TranslatorErrorKind::Unsupported(err.kind().to_string())
+            }
+            ast::ErrorKind::NestLimitExceeded(limit) => {
+                TranslatorErrorKind::NestLimitExceeded(*limit)
+            }
+            kind => TranslatorErrorKind::Syntax(kind.to_string()),
+        };
+        Self::new(kind, err.pattern(), *err.span())
+    }
+}
+
+impl From<hir::Error> for TranslatorError {
+    fn from(err: hir::Error) -> Self {
+        let kind = match err.kind() {
+            hir::ErrorKind::UnicodeNotAllowed | hir::ErrorKind::InvalidUtf8 => {
+                TranslatorErrorKind::Unsupported(err.kind().to_string())
+            }
+            kind => TranslatorErrorKind::Syntax(kind.to_string()),
+        };
+        Self::new(kind, err.pattern(), *err.span())
+    }
+}
+
+impl std::fmt::Display for ErrorCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ErrorCategory::Unsupported => write!(f, "unsupported feature"),
+            ErrorCategory::Syntax => write!(f, "syntax error"),
+            ErrorCategory::ResourceLimit => write!(f, "resource limit exceeded"),
+        }
+    }
+}
+
+impl std::fmt::Display for TranslatorErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TranslatorErrorKind::Unsupported(message) | TranslatorErrorKind::Syntax(message) => {
+                write!(f, "{}", message)
+            }
+            TranslatorErrorKind::NestLimitExceeded(limit) => {
+                write!(f, "nesting exceeds the nest limit of {}", limit)
+            }
+            TranslatorErrorKind::RepetitionLimitExceeded(limit) => {
+                write!(f, "repetition exceeds the repetition limit of {}", limit)
+            }
+        }
+    }
+}
+
+/// Renders the error followed by the line of the regular expression where it occurred,
+/// with the span of the error underlined:
+///
+/// ```text
+/// error when translating regular expression: resource limit exceeded: repetition exceeds the repetition limit of 1000
+///     ab{1001}
+///      ^^^^^^^
+/// ```
 impl std::fmt::Display for TranslatorError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let start = self.span.start.min(self.pattern.len());
+        let end = self.span.end.clamp(start, self.pattern.len());
+        let line_start = self.pattern[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = self.pattern[start..]
+            .find('\n')
+            .map_or(self.pattern.len(), |i| start + i);
+        let underline_offset = self.pattern[line_start..start].chars().count();
+        let underline_length = self.pattern[start..end.min(line_end)]
+            .chars()
+            .count()
+            .max(1);
+
+        writeln!(
+            f,
+            "error when translating regular expression: {}: {}",
+            self.category(),
+            self.kind
+        )?;
+        writeln!(f, "    {}", &self.pattern[line_start..line_end])?;
         write!(
             f,
-            "error when translating regular expression: {}",
-            match self {
-                TranslatorError::UnsupportedAst(ast) => {
-                    format!("{:?} is not a supported ast kind (yet)", ast)
+            "    {}{}",
+            " ".repeat(underline_offset),
+            "^".repeat(underline_length)
+        )
+    }
+}
+
+impl std::error::Error for TranslatorError {}
+
+/// Settings that control how a regular expression is translated into an automaton
+#[derive(Debug, Clone)]
+pub struct TranslatorConfig {
+    /// The largest count allowed in a counted repetition like ```a{3,8}```,
+    /// multiplied by the counts of the counted repetitions it is nested in, like in ```(a{3,8}){2}```.
+    ///
+    /// Every repetition is built by copying the repeated automaton, so this bounds
+    /// the number of copies of any part of the regular expression, and with it the number of states.
+    pub repetition_limit: u32,
+    /// Whether perl classes use their unicode or their ascii definitions
+    /// when the ```u``` flag is not set in the regular expression.
+    ///
+    /// With unicode definitions, ```\d``` is ```\p{Nd}```, ```\s``` is ```\p{White_Space}``` and
+    /// ```\w``` is ```[\p{Alphabetic}\p{M}\p{Nd}\p{Pc}\p{Join_Control}]```, as recommended by UTS#18.
+    ///
+    /// With ascii definitions, ```\d``` is ```[0-9]```, ```\s``` is ```[\t\n\v\f\r ]```
+    /// and ```\w``` is ```[0-9A-Za-z_]```. Case insensitive matching is also limited to ascii letters.
+    /// Classes that match bytes that are not ascii, like ```\D``` or ```[^a]```, are not valid utf-8
+    /// and are rejected as unsupported.
+    pub unicode: bool,
+    /// Whether matching is case insensitive when the ```i``` flag is not set in the regular expression
+    pub case_insensitive: bool,
+    /// Whether ```^``` and ```$``` match at the start and end of lines
+    /// when the ```m``` flag is not set in the regular expression
+    pub multi_line: bool,
+    /// Whether ```.``` matches ```\n``` when the ```s``` flag is not set in the regular expression
+    pub dot_matches_new_line: bool,
+    /// Whether whitespace and ```#``` comments are ignored when the ```x``` flag is not set in the regular expression
+    pub ignore_whitespace: bool,
+    /// Whether escapes like ```\141``` are octal literals.
+    ///
+    /// When disabled, they are rejected by the parser, since they look like backreferences.
+    pub octal: bool,
+    /// Which match is found when a string can be matched in more than one way,
+    /// like ```a+?``` in ```aaa```
+    pub match_kind: MatchKind,
+}
+
+impl Default for TranslatorConfig {
+    fn default() -> Self {
+        Self {
+            repetition_limit: 1000,
+            unicode: true,
+            case_insensitive: false,
+            multi_line: false,
+            dot_matches_new_line: false,
+            ignore_whitespace: false,
+            octal: false,
+            match_kind: MatchKind::LeftmostLongest,
+        }
+    }
+}
+
+/// Parses `s` into an ast, which regex_syntax then translates into its high-level intermediate representation (hir)
+/// with every flag applied and every class resolved into sorted ranges of symbols,
+/// and builds an automaton simulating the hir.
+pub(crate) fn translate(s: &str, config: &TranslatorConfig) -> TranslatorResult {
+    let mut ast = ParserBuilder::new()
+        .ignore_whitespace(config.ignore_whitespace)
+        .octal(config.octal)
+        .build()
+        .parse(s)
+        .map_err(TranslatorError::from)?;
+    prepare_ast(&mut ast, s, config, 1)?;
+    let mut flags = ClassFlags {
+        unicode: config.unicode,
+        case_insensitive: config.case_insensitive,
+    };
+    let mut next_index = u32::MAX;
+    replace_empty_classes(&mut ast, s, &mut flags, &mut next_index);
+    let hir = hir::translate::TranslatorBuilder::new()
+        .unicode(config.unicode)
+        .case_insensitive(config.case_insensitive)
+        .multi_line(config.multi_line)
+        .dot_matches_new_line(config.dot_matches_new_line)
+        .build()
+        .translate(s, &ast)
+        .map_err(TranslatorError::from)?;
+    let hir = restore_empty_classes(hir, next_index);
+    // Perl classes like ```(?-u)\D``` are not rejected by the hir even though they match bytes that are not ascii
+    if !hir.is_always_utf8() {
+        return Err(TranslatorError::new(
+            TranslatorErrorKind::Unsupported(hir::ErrorKind::InvalidUtf8.to_string()),
+            s,
+            *ast.span(),
+        ));
+    }
+    let mut automaton = build_tree(&hir)?;
+    automaton.set_match_kind(config.match_kind);
+    let mut capture_names = Vec::new();
+    collect_capture_names(&hir, &mut capture_names);
+    if !capture_names.is_empty() {
+        // Group 0 is the whole match, which is never named
+        capture_names[0] = None;
+        automaton.set_capture_names(capture_names);
+    }
+    Ok(automaton)
+}
+
+/// Stores the name of every capture group in `hir` at its index in `capture_names`,
+/// growing it to fit the largest index
+fn collect_capture_names(hir: &Hir, capture_names: &mut Vec<Option<String>>) {
+    match hir.kind() {
+        HirKind::Group(group) => {
+            let (index, name) = match &group.kind {
+                hir::GroupKind::CaptureIndex(index) => (Some(*index as usize), None),
+                hir::GroupKind::CaptureName { name, index } => {
+                    (Some(*index as usize), Some(name.clone()))
                 }
-                TranslatorError::UnsupportedClass(class) => {
-                    format!("{:?} is not a supported class kind (yet)", class)
+                hir::GroupKind::NonCapturing => (None, None),
+            };
+            if let Some(index) = index {
+                if capture_names.len() <= index {
+                    capture_names.resize(index + 1, None);
                 }
-                TranslatorError::UnsupportedClassSet(class_set) => {
-                    format!("{:?} is not a supported class set kind (yet)", class_set)
+                capture_names[index] = name;
+            }
+            collect_capture_names(&group.hir, capture_names);
+        }
+        HirKind::Repetition(repetition) => collect_capture_names(&repetition.hir, capture_names),
+        HirKind::Concat(hirs) | HirKind::Alternation(hirs) => hirs
+            .iter()
+            .for_each(|hir| collect_capture_names(hir, capture_names)),
+        _ => {}
+    }
+}
+
+/// The flags that decide which symbols a class matches
+#[derive(Clone, Copy)]
+struct ClassFlags {
+    unicode: bool,
+    case_insensitive: bool,
+}
+
+impl ClassFlags {
+    fn apply(&mut self, flags: &ast::Flags) {
+        if let Some(unicode) = flags.flag_state(ast::Flag::Unicode) {
+            self.unicode = unicode;
+        }
+        if let Some(case_insensitive) = flags.flag_state(ast::Flag::CaseInsensitive) {
+            self.case_insensitive = case_insensitive;
+        }
+    }
+}
+
+/// Replaces every class in `ast` that does not match any symbol, like ```[a&&b]``` or ```\P{Any}```,
+/// which the hir does not allow, by an empty capture group with index `next_index`, counting down from ```u32::MAX```
+/// so that it is larger than the index of any real group.
+///
+/// Whether a class is empty depends on the `flags` it is in, which are tracked like the hir does:
+/// flags set by ```(?i)``` apply until the end of the enclosing group.
+fn replace_empty_classes(
+    ast: &mut Ast,
+    pattern: &str,
+    flags: &mut ClassFlags,
+    next_index: &mut u32,
+) {
+    match ast {
+        Ast::Flags(set_flags) => flags.apply(&set_flags.flags),
+        Ast::Class(class) => {
+            let translated = hir::translate::TranslatorBuilder::new()
+                .unicode(flags.unicode)
+                .case_insensitive(flags.case_insensitive)
+                .build()
+                .translate(pattern, &Ast::Class(class.clone()));
+            if matches!(translated, Err(error) if *error.kind() == hir::ErrorKind::EmptyClassNotAllowed)
+            {
+                let span = *class.span();
+                *ast = Ast::Group(Group {
+                    span,
+                    kind: GroupKind::CaptureIndex(*next_index),
+                    ast: Box::new(Ast::Empty(span)),
+                });
+                *next_index -= 1;
+            }
+        }
+        Ast::Repetition(repetition) => {
+            replace_empty_classes(&mut repetition.ast, pattern, flags, next_index)
+        }
+        Ast::Group(group) => {
+            let mut group_flags = *flags;
+            if let Some(set_flags) = group.flags() {
+                group_flags.apply(set_flags);
+            }
+            replace_empty_classes(&mut group.ast, pattern, &mut group_flags, next_index)
+        }
+        Ast::Alternation(alternation) => alternation
+            .asts
+            .iter_mut()
+            .for_each(|ast| replace_empty_classes(ast, pattern, flags, next_index)),
+        Ast::Concat(concat) => concat
+            .asts
+            .iter_mut()
+            .for_each(|ast| replace_empty_classes(ast, pattern, flags, next_index)),
+        _ => {}
+    }
+}
+
+/// Replaces every group in `hir` with an index larger than `next_index`, which stands in for an empty class,
+/// by the empty class
+fn restore_empty_classes(hir: Hir, next_index: u32) -> Hir {
+    match hir.into_kind() {
+        HirKind::Group(hir::Group {
+            kind: hir::GroupKind::CaptureIndex(index),
+            ..
+        }) if index > next_index => Hir::class(hir::Class::Unicode(ClassUnicode::empty())),
+        HirKind::Group(group) => Hir::group(hir::Group {
+            kind: group.kind,
+            hir: Box::new(restore_empty_classes(*group.hir, next_index)),
+        }),
+        HirKind::Repetition(repetition) => Hir::repetition(hir::Repetition {
+            hir: Box::new(restore_empty_classes(*repetition.hir, next_index)),
+            ..repetition
+        }),
+        HirKind::Concat(hirs) => Hir::concat(
+            hirs.into_iter()
+                .map(|hir| restore_empty_classes(hir, next_index))
+                .collect(),
+        ),
+        HirKind::Alternation(hirs) => Hir::alternation(
+            hirs.into_iter()
+                .map(|hir| restore_empty_classes(hir, next_index))
+                .collect(),
+        ),
+        HirKind::Empty => Hir::empty(),
+        HirKind::Literal(literal) => Hir::literal(literal),
+        HirKind::Class(class) => Hir::class(class),
+        HirKind::Anchor(anchor) => Hir::anchor(anchor),
+        HirKind::WordBoundary(word_boundary) => Hir::word_boundary(word_boundary),
+    }
+}
+
+/// Checks every counted repetition in `ast` against the repetition limit of `config`, where `copies` is the product
+/// of the counts of the repetitions `ast` is nested in, and works around two bugs in how regex_syntax translates an ast into a hir:
+///
+/// * The ```!=``` operator is ignored in unicode classes like ```\p{sc!=Greek}```,
+///   so they are rewritten into the equivalent ```\P{sc=Greek}```.
+/// * Flags set inside a capturing group, like in ```(a(?i)b)c```, also apply after the group,
+///   so the contents of every capturing group are wrapped in a non-capturing group, which does restore them.
+fn prepare_ast(
+    ast: &mut Ast,
+    pattern: &str,
+    config: &TranslatorConfig,
+    copies: u64,
+) -> Result<(), TranslatorError> {
+    match ast {
+        Ast::Repetition(repetition) => {
+            let mut inner_copies = copies;
+            if let RepetitionKind::Range(range) = &repetition.op.kind {
+                let max = match *range {
+                    RepetitionRange::Exactly(n) | RepetitionRange::AtLeast(n) => n,
+                    RepetitionRange::Bounded(_, m) => m,
+                };
+                // The repeated automaton is built once even if it is repeated zero times
+                inner_copies = copies.saturating_mul(u64::from(max.max(1)));
+                if copies.saturating_mul(u64::from(max)) > u64::from(config.repetition_limit) {
+                    return Err(TranslatorError::new(
+                        TranslatorErrorKind::RepetitionLimitExceeded(config.repetition_limit),
+                        pattern,
+                        repetition.span,
+                    ));
                 }
-                TranslatorError::UnsupportedClassSetItem(class_set_item) => format!(
-                    "{:?} is not a supported class set item kind (yet)",
-                    class_set_item
-                ),
-                TranslatorError::ParserError(parser_error) => parser_error.to_string(),
             }
-        )
+            prepare_ast(&mut repetition.ast, pattern, config, inner_copies)
+        }
+        Ast::Group(group) => {
+            if group.is_capturing() {
+                let group_ast = std::mem::replace(&mut *group.ast, Ast::Empty(group.span));
+                *group.ast = Ast::Group(Group {
+                    span: *group_ast.span(),
+                    kind: GroupKind::NonCapturing(ast::Flags {
+                        span: *group_ast.span(),
+                        items: Vec::new(),
+                    }),
+                    ast: Box::new(group_ast),
+                });
+            }
+            prepare_ast(&mut group.ast, pattern, config, copies)
+        }
+        Ast::Alternation(alternation) => alternation
+            .asts
+            .iter_mut()
+            .try_for_each(|ast| prepare_ast(ast, pattern, config, copies)),
+        Ast::Concat(concat) => concat
+            .asts
+            .iter_mut()
+            .try_for_each(|ast| prepare_ast(ast, pattern, config, copies)),
+        Ast::Class(Class::Unicode(class_unicode)) => {
+            rewrite_not_equal(class_unicode);
+            Ok(())
+        }
+        Ast::Class(Class::Bracketed(class_bracketed)) => {
+            prepare_class_set(&mut class_bracketed.kind);
+            Ok(())
+        }
+        _ => Ok(()),
     }
 }
 
-impl std::error::Error for TranslatorError {}
+fn prepare_class_set(set: &mut ClassSet) {
+    match set {
+        ClassSet::Item(item) => prepare_class_set_item(item),
+        ClassSet::BinaryOp(binary_op) => {
+            prepare_class_set(&mut binary_op.lhs);
+            prepare_class_set(&mut binary_op.rhs);
+        }
+    }
+}
 
-pub(crate) fn translate(s: &str) -> TranslatorResult {
-    match Parser::new().parse(s) {
-        Ok(ast) => build_tree(&ast),
-        Err(err) => Err(TranslatorError::ParserError(err)),
+fn prepare_class_set_item(item: &mut ClassSetItem) {
+    match item {
+        ClassSetItem::Unicode(class_unicode) => rewrite_not_equal(class_unicode),
+        ClassSetItem::Bracketed(class_bracketed) => prepare_class_set(&mut class_bracketed.kind),
+        ClassSetItem::Union(union) => union.items.iter_mut().for_each(prepare_class_set_item),
+        _ => {}
     }
 }
 
-fn build_tree(ast_tree: &Ast) -> TranslatorResult {
-    match ast_tree {
-        Ast::Concat(ast) => build_concatenation(ast),
-        Ast::Repetition(ast) => build_repetition(ast),
-        Ast::Literal(ast) => build_literal(std::iter::once(ast.c).collect()),
-        Ast::Alternation(ast) => build_alternation(ast),
-        Ast::Group(ast) => build_tree(&ast.ast),
-        Ast::Class(ast) => build_class(ast),
-        unsupported => Err(TranslatorError::UnsupportedAst(unsupported.clone())),
+/// Rewrites a unicode class like ```\p{sc!=Greek}``` into ```\P{sc=Greek}```
+fn rewrite_not_equal(class_unicode: &mut ast::ClassUnicode) {
+    if let ClassUnicodeKind::NamedValue { op, .. } = &mut class_unicode.kind {
+        if *op == ClassUnicodeOpKind::NotEqual {
+            *op = ClassUnicodeOpKind::Equal;
+            class_unicode.negated = !class_unicode.negated;
+        }
     }
 }
 
-fn build_class(class_ast: &Class) -> TranslatorResult {
-    match class_ast {
-        Class::Bracketed(class_bracketed) => match &class_bracketed.kind {
-            ClassSet::Item(item) => match item {
-                ClassSetItem::Range(class_set_range) => build_class_set_range(&class_set_range),
-                unsupported => Err(TranslatorError::UnsupportedClassSetItem(
-                    unsupported.clone(),
-                )),
-            },
-            unsupported => Err(TranslatorError::UnsupportedClassSet(unsupported.clone())),
+fn build_tree(hir: &Hir) -> TranslatorResult {
+    match hir.kind() {
+        // The empty pattern, an empty group like ```()``` or an empty alternative like in ```a|```
+        HirKind::Empty => Ok(concatenate(Vec::new())),
+        HirKind::Literal(hir::Literal::Unicode(c)) => {
+            build_literal(&class_from_ranges(&[(*c, *c)]))
+        }
+        // Since the input is always valid utf-8, the hir rejects bytes that are not ascii,
+        // and ascii bytes are matched as the same symbols
+        HirKind::Literal(hir::Literal::Byte(byte)) => {
+            let c = char::from(*byte);
+            build_literal(&class_from_ranges(&[(c, c)]))
+        }
+        HirKind::Class(hir::Class::Unicode(class)) => build_literal(class),
+        HirKind::Class(hir::Class::Bytes(class)) => {
+            build_literal(&ClassUnicode::new(class.iter().map(|range| {
+                ClassUnicodeRange::new(char::from(range.start()), char::from(range.end()))
+            })))
+        }
+        HirKind::Anchor(anchor) => build_assertion(match anchor {
+            hir::Anchor::StartLine => Look::StartLine,
+            hir::Anchor::EndLine => Look::EndLine,
+            hir::Anchor::StartText => Look::StartText,
+            hir::Anchor::EndText => Look::EndText,
+        }),
+        HirKind::WordBoundary(word_boundary) => build_assertion(match word_boundary {
+            hir::WordBoundary::Unicode => Look::WordBoundary,
+            hir::WordBoundary::UnicodeNegate => Look::NotWordBoundary,
+            hir::WordBoundary::Ascii => Look::WordBoundaryAscii,
+            hir::WordBoundary::AsciiNegate => Look::NotWordBoundaryAscii,
+        }),
+        HirKind::Repetition(repetition) => build_repetition(repetition),
+        HirKind::Group(group) => match group.kind {
+            hir::GroupKind::CaptureIndex(index) | hir::GroupKind::CaptureName { index, .. } => {
+                Ok(build_capture_group(build_tree(&group.hir)?, index as usize))
+            }
+            hir::GroupKind::NonCapturing => build_tree(&group.hir),
         },
-        unsupported => Err(TranslatorError::UnsupportedClass(unsupported.clone())),
+        HirKind::Concat(hirs) => build_concatenation(hirs),
+        HirKind::Alternation(hirs) => build_alternation(hirs),
     }
 }
 
-/// Builds an automaton simulating a regular expression like ```[a-z]```
-/// by just building a literal with each symbol in the range as its transition
-fn build_class_set_range(class_set_range: &ClassSetRange) -> TranslatorResult {
-    let start_atom = class_set_range.start.c as u8;
-    let end_atom = class_set_range.end.c as u8;
+/// Builds an automaton simulating an assertion like ```^``` or ```\b```,
+/// which only matches the empty string at positions where `look` holds
+fn build_assertion(look: Look) -> TranslatorResult {
+    let mut assertion_automaton = Automaton::new();
+    let start_state = assertion_automaton.add_state();
+    let end_state = assertion_automaton.add_state();
+    assertion_automaton.set_accepting(end_state, true);
+    assertion_automaton.set_start_state(start_state);
+    assertion_automaton.add_look_transition(start_state, end_state, look);
+    Ok(assertion_automaton)
+}
+
+/// Builds an automaton simulating a capture group like ```(a)``` or ```(?P<name>a)```
+/// by wrapping `inner_automaton` between a new start and end state, with epsilon transitions
+/// tagging the start and the end of group `index`
+fn build_capture_group(inner_automaton: Automaton, index: usize) -> Automaton {
+    let mut group_automaton = Automaton::new();
+    let group_start_state = group_automaton.add_state();
+    let group_end_state = group_automaton.add_state();
+    let group_to_inner_offset = group_automaton.states;
+
+    let inner_automaton_start_state = inner_automaton.start_state;
+    let inner_automaton_end_states: Vec<u32> =
+        inner_automaton.accepting_states.iter().copied().collect();
+    group_automaton.add_states_and_transitions(inner_automaton);
+
+    group_automaton.add_tagged_transition(
+        group_start_state,
+        inner_automaton_start_state + group_to_inner_offset,
+        2 * index,
+    );
+    for inner_automaton_end_state in inner_automaton_end_states {
+        group_automaton.add_tagged_transition(
+            inner_automaton_end_state + group_to_inner_offset,
+            group_end_state,
+            2 * index + 1,
+        );
+    }
+
+    group_automaton.set_start_state(group_start_state);
+    group_automaton.set_accepting(group_end_state, true);
+    group_automaton
+}
 
-    build_literal(((start_atom..=end_atom).map(char::from)).collect())
+fn class_from_ranges(ranges: &[(char, char)]) -> ClassUnicode {
+    ClassUnicode::new(
+        ranges
+            .iter()
+            .map(|&(start, end)| ClassUnicodeRange::new(start, end)),
+    )
 }
 
 /// Builds an automaton simulating a regular expression like ```abc```
 /// by appending each symbol to the end state of the previous symbol, a -> b -> _c_
-fn build_concatenation(concat_ast: &Concat) -> TranslatorResult {
+fn build_concatenation(hirs: &[Hir]) -> TranslatorResult {
+    let mut append_automatons = Vec::with_capacity(hirs.len());
+    for append_hir in hirs {
+        append_automatons.push(build_tree(append_hir)?);
+    }
+
+    Ok(concatenate(append_automatons))
+}
+
+/// Builds an automaton simulating the automatons in `append_automatons` one after the other,
+/// by adding an epsilon transition from the end (accepting) states of each automaton to the start state of the next.
+/// If `append_automatons` is empty, the result only matches the empty string.
+pub(crate) fn concatenate(append_automatons: Vec<Automaton>) -> Automaton {
     let mut concat_automaton = Automaton::new();
     let concat_start_state = concat_automaton.add_state();
     concat_automaton.set_start_state(concat_start_state);
 
-    let mut concat_end_state = concat_start_state;
+    let mut concat_end_states = vec![concat_start_state];
 
-    for append_ast in &concat_ast.asts {
-        let append_automaton = build_tree(append_ast)?;
-        assert_eq!(append_automaton.accepting_states.len(), 1);
+    for append_automaton in append_automatons {
         let append_start_state = append_automaton.start_state;
-        let append_end_state = *append_automaton.accepting_states.iter().next().unwrap();
+        let append_end_states: Vec<u32> =
+            append_automaton.accepting_states.iter().copied().collect();
         let concat_append_offset = concat_automaton.states;
         concat_automaton.add_states_and_transitions(append_automaton);
 
-        // Add transition from previous append_automaton's end state to current append_automaton's start state
-        concat_automaton.add_transition(
-            concat_end_state,
-            append_start_state + concat_append_offset,
-            None,
-        );
+        // Add transitions from previous append_automaton's end states to current append_automaton's start state
+        for concat_end_state in concat_end_states {
+            concat_automaton.add_transition(
+                concat_end_state,
+                append_start_state + concat_append_offset,
+                None,
+            );
+        }
+
+        // Change end states to be the current append_automaton's end states
+        concat_end_states = append_end_states
+            .into_iter()
+            .map(|append_end_state| append_end_state + concat_append_offset)
+            .collect();
+    }
 
-        // Change end state to be the current append_automaton's end state
-        concat_end_state = append_end_state + concat_append_offset;
-        concat_automaton.clear_accepting();
+    for concat_end_state in concat_end_states {
         concat_automaton.set_accepting(concat_end_state, true);
     }
+    concat_automaton
+}
+
+/// Builds an automaton simulating a regular expression like ```a?```, ```a+```, ```a*``` or ```a{n,m}```.
+/// Whether the repetition is greedy does not change which strings are matched, only their captures.
+fn build_repetition(repetition: &hir::Repetition) -> TranslatorResult {
+    let inner_automaton = build_tree(&repetition.hir)?;
+    let greedy = repetition.greedy;
 
-    Ok(concat_automaton)
+    match &repetition.kind {
+        hir::RepetitionKind::ZeroOrOne => Ok(repeat(inner_automaton, true, false, greedy)),
+        hir::RepetitionKind::ZeroOrMore => Ok(repeat(inner_automaton, true, true, greedy)),
+        hir::RepetitionKind::OneOrMore => Ok(repeat(inner_automaton, false, true, greedy)),
+        hir::RepetitionKind::Range(repetition_range) => {
+            let (min, max) = match *repetition_range {
+                hir::RepetitionRange::Exactly(n) => (n, Some(n)),
+                hir::RepetitionRange::AtLeast(n) => (n, None),
+                hir::RepetitionRange::Bounded(n, m) => (n, Some(m)),
+            };
+            Ok(build_counted_repetition(inner_automaton, min, max, greedy))
+        }
+    }
 }
 
-/// Builds an automaton simulating a regular expression like ```a?```, ```a+``` or ```a*```
-/// For ```?```, create two states with the repeating automaton between them, and add an epsilon
-/// transition from the starting state to the end (accepting) state.
-/// For ```+```, create two states with the repeating automaton between them, and add an epsilon
-/// transition from the end (accepting) state to the starting state.
-/// For ```*```, create two states with the repeating automaton between them, and add an epsilon
-/// transition from the starting state to the end (accepting) state, and an epsilon transition from
-/// the end (accepting) state to the starting state.
-fn build_repetition(repetition_ast: &Repetition) -> TranslatorResult {
-    use regex_syntax::ast::RepetitionKind;
+/// Builds an automaton simulating a regular expression like ```a{n}```, ```a{n,}``` or ```a{n,m}```
+/// by concatenating `min` copies of the repeated automaton, followed by either a ```*``` of the
+/// repeated automaton (if there is no upper bound), or `max - min` copies of ```?``` of the repeated automaton.
+fn build_counted_repetition(
+    inner_automaton: Automaton,
+    min: u32,
+    max: Option<u32>,
+    greedy: bool,
+) -> Automaton {
+    let mut append_automatons: Vec<Automaton> = (0..min).map(|_| inner_automaton.clone()).collect();
+    match max {
+        Some(max) => {
+            for _ in min..max {
+                append_automatons.push(repeat(inner_automaton.clone(), true, false, greedy));
+            }
+        }
+        None => append_automatons.push(repeat(inner_automaton, true, true, greedy)),
+    }
+
+    concatenate(append_automatons)
+}
 
+/// Wraps `inner_automaton` between a new start and end state.
+/// If `optional` is set, add an epsilon transition from the start state to the end (accepting) state (```?```).
+/// If `repeating` is set, add an epsilon transition from the end states of `inner_automaton` back to the start state (```+```).
+/// If both are set, the result simulates ```*```.
+/// If `greedy` is set, matching `inner_automaton` again is preferred to moving on, otherwise moving on is preferred.
+pub(crate) fn repeat(
+    inner_automaton: Automaton,
+    optional: bool,
+    repeating: bool,
+    greedy: bool,
+) -> Automaton {
     let mut repetition_automaton = Automaton::new();
     let repetition_start_state = repetition_automaton.add_state();
     let repetition_end_state = repetition_automaton.add_state();
     let repetition_to_inner_offset = repetition_automaton.states;
 
-    let inner_automaton = build_tree(&repetition_ast.ast)?;
-    assert_eq!(inner_automaton.accepting_states.len(), 1);
     let inner_automaton_start_state = inner_automaton.start_state;
-    let inner_automaton_end_state = *inner_automaton.accepting_states.iter().next().unwrap();
+    let inner_automaton_end_states: Vec<u32> =
+        inner_automaton.accepting_states.iter().copied().collect();
     repetition_automaton.add_states_and_transitions(inner_automaton);
 
-    // Add transition from repetition_automaton's start state to inner_automaton's start state
-    repetition_automaton.add_transition(
-        repetition_start_state,
-        inner_automaton_start_state + repetition_to_inner_offset,
-        None,
-    );
+    // Epsilon transitions are preferred in the order they are added
+    let preferred = |again_state: u32, on_state: u32| {
+        if greedy {
+            [again_state, on_state]
+        } else {
+            [on_state, again_state]
+        }
+    };
 
-    // Add transition from inner_automaton's end state to repetition_automaton's end state
-    repetition_automaton.add_transition(
-        inner_automaton_end_state + repetition_to_inner_offset,
-        repetition_end_state,
-        None,
-    );
+    let inner_start_state = inner_automaton_start_state + repetition_to_inner_offset;
+    let start_to_states = if optional {
+        // Add transitions from repetition_automaton's start state to inner_automaton's start state,
+        // and to repetition_automaton's end state (for Zero)
+        preferred(inner_start_state, repetition_end_state).to_vec()
+    } else {
+        // Add transition from repetition_automaton's start state to inner_automaton's start state
+        vec![inner_start_state]
+    };
+    for to_state in start_to_states {
+        repetition_automaton.add_transition(repetition_start_state, to_state, None);
+    }
 
-    match &repetition_ast.op.kind {
-        RepetitionKind::OneOrMore => {
-            // Add transition from repetition_automaton's end state to repetition_automaton's start state
-            repetition_automaton.add_transition(repetition_end_state, repetition_start_state, None);
-        }
-        RepetitionKind::ZeroOrMore => {
-            // Add transition from repetition_automaton's start state to repetition_automaton's end state (for Zero)
-            repetition_automaton.add_transition(repetition_start_state, repetition_end_state, None);
-            // Add transition from repetition_automaton's end state to repetition_automaton's start state
-            repetition_automaton.add_transition(repetition_end_state, repetition_start_state, None);
-        }
-        RepetitionKind::ZeroOrOne => {
-            // Add transition from repetition_automaton's start state to repetition_automaton's end state
-            repetition_automaton.add_transition(repetition_start_state, repetition_end_state, None);
-        }
-        unsupported => {
-            panic!("{:?} is not supported yet", unsupported);
+    // Add transitions from inner_automaton's end states to repetition_automaton's end state,
+    // and back to inner_automaton's start state if repeating
+    for inner_automaton_end_state in inner_automaton_end_states {
+        let end_to_states = if repeating {
+            preferred(inner_start_state, repetition_end_state).to_vec()
+        } else {
+            vec![repetition_end_state]
+        };
+        for to_state in end_to_states {
+            repetition_automaton.add_transition(
+                inner_automaton_end_state + repetition_to_inner_offset,
+                to_state,
+                None,
+            );
         }
     }
 
@@ -179,24 +766,22 @@ fn build_repetition(repetition_ast: &Repetition) -> TranslatorResult {
     repetition_automaton.clear_accepting();
     repetition_automaton.set_accepting(repetition_end_state, true);
 
-    Ok(repetition_automaton)
+    repetition_automaton
 }
 
-fn build_alternation(alternation_ast: &Alternation) -> TranslatorResult {
+fn build_alternation(hirs: &[Hir]) -> TranslatorResult {
     let mut alternation_automaton = Automaton::new();
     let alternation_automaton_start_state = alternation_automaton.add_state();
     let alternation_automaton_end_state = alternation_automaton.add_state();
 
-    for alternative_ast in &alternation_ast.asts {
-        let alternative_automaton = build_tree(alternative_ast)?;
-        assert_eq!(alternative_automaton.accepting_states.len(), 1);
-
+    for alternative_hir in hirs {
+        let alternative_automaton = build_tree(alternative_hir)?;
         let alternative_automaton_start_state = alternative_automaton.start_state;
-        let alternative_automaton_end_state = *alternative_automaton
+        let alternative_automaton_end_states: Vec<u32> = alternative_automaton
             .accepting_states
             .iter()
-            .next()
-            .unwrap();
+            .copied()
+            .collect();
         let alternation_to_alternative_offset = alternation_automaton.states;
         alternation_automaton.add_states_and_transitions(alternative_automaton);
 
@@ -207,12 +792,14 @@ fn build_alternation(alternation_ast: &Alternation) -> TranslatorResult {
             None,
         );
 
-        // Add transition from alternative_automaton's end state to alternation_automaton's end state
-        alternation_automaton.add_transition(
-            alternative_automaton_end_state + alternation_to_alternative_offset,
-            alternation_automaton_end_state,
-            None,
-        );
+        // Add transitions from alternative_automaton's end states to alternation_automaton's end state
+        for alternative_automaton_end_state in alternative_automaton_end_states {
+            alternation_automaton.add_transition(
+                alternative_automaton_end_state + alternation_to_alternative_offset,
+                alternation_automaton_end_state,
+                None,
+            );
+        }
     }
 
     alternation_automaton.set_start_state(alternation_automaton_start_state);
@@ -222,14 +809,20 @@ fn build_alternation(alternation_ast: &Alternation) -> TranslatorResult {
     Ok(alternation_automaton)
 }
 
-fn build_literal(atoms: HashSet<char>) -> TranslatorResult {
+/// Builds an automaton simulating a single symbol from `atoms`,
+/// with a transition for each range of symbols in the class
+fn build_literal(atoms: &ClassUnicode) -> TranslatorResult {
     let mut literal_automaton = Automaton::new();
     let start_state = literal_automaton.add_state();
     let end_state = literal_automaton.add_state();
     literal_automaton.set_accepting(end_state, true);
     literal_automaton.set_start_state(start_state);
-    for atom in atoms {
-        literal_automaton.add_transition(start_state, end_state, Some(atom));
+    for range in atoms.iter() {
+        literal_automaton.add_range_transition(
+            start_state,
+            end_state,
+            CharRange::new(range.start(), range.end()),
+        );
     }
     Ok(literal_automaton)
 }
@@ -1,5 +1,5 @@
-use regex_dfa::automaton::Automaton;
-use regex_dfa::translator::TranslatorError;
+use regex_dfa::automaton::{Automaton, CharRange, DfaLimits, LimitError, LookError, MatchKind};
+use regex_dfa::translator::{ErrorCategory, TranslatorConfig, TranslatorErrorKind};
 
 #[test]
 fn test_concatenation_whole_1() {
@@ -53,6 +53,30 @@ fn test_concatenation_substrings_2() {
     );
 }
 
+#[test]
+fn test_match_byte_offsets_1() {
+    // Matches are ranges of byte offsets into the input, so they can be used to slice it
+    let automaton = Automaton::from_string("é+").unwrap().into_min_dfa();
+    assert_eq!(automaton.match_longest_prefix("ééx"), Some(0..4));
+    assert_eq!(automaton.match_first_prefix("éx"), Some("é"));
+    assert_eq!(automaton.match_substrings("xé"), vec![1..3]);
+    assert_eq!(automaton.match_longest_substring("xééx"), Some(1..5));
+    assert_eq!(&"xééx"[1..5], "éé");
+}
+
+#[test]
+fn test_match_longest_prefix_1() {
+    // The longest prefix ends after the last symbol it reads, even at the end of the input
+    let automaton = Automaton::from_string("ab|abcd").unwrap().into_min_dfa();
+    assert_eq!(automaton.match_longest_prefix("ab"), Some(0..2));
+    assert_eq!(automaton.match_longest_prefix("abc"), Some(0..2));
+    assert_eq!(automaton.match_longest_prefix("abcde"), Some(0..4));
+    assert_eq!(automaton.match_longest_prefix("a"), None);
+
+    let automaton = Automaton::from_string("a*").unwrap().into_min_dfa();
+    assert_eq!(automaton.match_longest_prefix("b"), Some(0..0));
+}
+
 #[test]
 fn test_alternation_1() {
     let automaton = Automaton::from_string("a|b").unwrap().into_min_dfa();
@@ -109,6 +133,352 @@ fn test_literal_range_1() {
     assert!(!automaton.match_whole("1"));
 }
 
+#[test]
+fn test_literal_range_non_ascii_1() {
+    let automaton = Automaton::from_string("[α-ω]+").unwrap().into_min_dfa();
+    assert!(automaton.match_whole("λογος"));
+    assert!(!automaton.match_whole("λόγος"));
+    assert!(automaton.match_whole("αω"));
+    assert!(!automaton.match_whole("Α"));
+    assert!(!automaton.match_whole("a"));
+    assert!(!automaton.match_whole("ѱ"));
+}
+
+#[test]
+fn test_literal_range_non_ascii_2() {
+    let automaton = Automaton::from_string("[\\u{100}-\\u{200}]")
+        .unwrap()
+        .into_min_dfa();
+    assert!(automaton.match_whole("\u{100}"));
+    assert!(automaton.match_whole("\u{180}"));
+    assert!(automaton.match_whole("\u{200}"));
+    assert!(!automaton.match_whole("\u{FF}"));
+    assert!(!automaton.match_whole("\u{201}"));
+    // The low byte of the range must not be matched on its own
+    assert!(!automaton.match_whole("\u{0}"));
+    assert!(!automaton.match_whole("\u{80}"));
+}
+
+#[test]
+fn test_literal_range_surrogates_1() {
+    // The range spans the surrogate code points, which are not unicode scalar values
+    let automaton = Automaton::from_string("[\\u{D7FF}-\\u{E000}]")
+        .unwrap()
+        .into_min_dfa();
+    assert!(automaton.match_whole("\u{D7FF}"));
+    assert!(automaton.match_whole("\u{E000}"));
+    assert!(!automaton.match_whole("\u{D7FE}"));
+    assert!(!automaton.match_whole("\u{E001}"));
+}
+
+#[test]
+fn test_literal_range_astral_1() {
+    let automaton = Automaton::from_string("[\\u{1F600}-\\u{1F64F}]+")
+        .unwrap()
+        .into_min_dfa();
+    assert!(automaton.match_whole("😀🙏"));
+    assert!(!automaton.match_whole("🚀"));
+    assert!(!automaton.match_whole("\u{F600}"));
+}
+
+#[test]
+fn test_literal_range_non_ascii_substrings_1() {
+    let automaton = Automaton::from_string("[α-ω]+").unwrap().into_min_dfa();
+    let input_str = "abγδe";
+    // Ranges are byte offsets into input_str, and every greek letter is two bytes long
+    assert_eq!(
+        automaton.match_substrings(input_str),
+        vec![2..4, 2..6, 4..6]
+    );
+    let longest_substring = automaton.match_longest_substring(input_str).unwrap();
+    assert_eq!(&input_str[longest_substring], "γδ");
+}
+
+#[test]
+fn test_class_union_1() {
+    let automaton = Automaton::from_string("[abc]").unwrap().into_min_dfa();
+    assert!(automaton.match_whole("a"));
+    assert!(automaton.match_whole("b"));
+    assert!(automaton.match_whole("c"));
+    assert!(!automaton.match_whole("d"));
+    assert!(!automaton.match_whole("ab"));
+}
+
+#[test]
+fn test_class_union_2() {
+    let automaton = Automaton::from_string("[a-z0-9_]+").unwrap().into_min_dfa();
+    assert!(automaton.match_whole("snake_case_2"));
+    assert!(!automaton.match_whole("camelCase"));
+    assert!(!automaton.match_whole(""));
+}
+
+#[test]
+fn test_class_union_3() {
+    // Without the surrounding brackets, this is a union of literals and not an ascii class
+    let automaton = Automaton::from_string("[:alnum:]").unwrap().into_min_dfa();
+    assert!(automaton.match_whole(":"));
+    assert!(automaton.match_whole("m"));
+    assert!(!automaton.match_whole("b"));
+}
+
+#[test]
+fn test_class_negated_1() {
+    let automaton = Automaton::from_string("[^x]").unwrap().into_min_dfa();
+    assert!(!automaton.match_whole("x"));
+    assert!(automaton.match_whole("y"));
+    assert!(automaton.match_whole("\n"));
+    assert!(automaton.match_whole("é"));
+    assert!(automaton.match_whole("\u{10FFFF}"));
+    assert!(!automaton.match_whole("yy"));
+}
+
+#[test]
+fn test_class_negated_2() {
+    let automaton = Automaton::from_string("a[^a-c]*c").unwrap().into_min_dfa();
+    assert!(automaton.match_whole("ac"));
+    assert!(automaton.match_whole("axyzc"));
+    assert!(!automaton.match_whole("abc"));
+    assert!(!automaton.match_whole("axycc"));
+}
+
+#[test]
+fn test_class_nested_1() {
+    let automaton = Automaton::from_string("[[a-c][0-2]]+")
+        .unwrap()
+        .into_min_dfa();
+    assert!(automaton.match_whole("a0b1c2"));
+    assert!(!automaton.match_whole("d"));
+    assert!(!automaton.match_whole("3"));
+}
+
+#[test]
+fn test_class_nested_2() {
+    let automaton = Automaton::from_string("[a-z[^m-z]]")
+        .unwrap()
+        .into_min_dfa();
+    assert!(automaton.match_whole("a"));
+    assert!(automaton.match_whole("z"));
+    assert!(automaton.match_whole("0"));
+    assert!(automaton.match_whole("ö"));
+}
+
+#[test]
+fn test_class_ascii_1() {
+    let automaton = Automaton::from_string("[[:alpha:]][[:alnum:]_]*")
+        .unwrap()
+        .into_min_dfa();
+    assert!(automaton.match_whole("identifier_1"));
+    assert!(!automaton.match_whole("1identifier"));
+    assert!(!automaton.match_whole("ident-ifier"));
+}
+
+#[test]
+fn test_class_ascii_2() {
+    let automaton = Automaton::from_string("[[:^digit:]]")
+        .unwrap()
+        .into_min_dfa();
+    assert!(automaton.match_whole("a"));
+    assert!(automaton.match_whole("٣"));
+    assert!(!automaton.match_whole("3"));
+}
+
+#[test]
+fn test_class_intersection_1() {
+    let automaton = Automaton::from_string("[a-z&&[^aeiou]]+")
+        .unwrap()
+        .into_min_dfa();
+    assert!(automaton.match_whole("rhythm"));
+    assert!(!automaton.match_whole("vowel"));
+    assert!(!automaton.match_whole("RHYTHM"));
+}
+
+#[test]
+fn test_class_difference_1() {
+    let automaton = Automaton::from_string("[a-z--[aeiou]]")
+        .unwrap()
+        .into_min_dfa();
+    assert!(automaton.match_whole("b"));
+    assert!(automaton.match_whole("z"));
+    assert!(!automaton.match_whole("a"));
+    assert!(!automaton.match_whole("u"));
+}
+
+#[test]
+fn test_class_symmetric_difference_1() {
+    let automaton = Automaton::from_string("[a-f~~d-h]").unwrap().into_min_dfa();
+    assert!(automaton.match_whole("a"));
+    assert!(automaton.match_whole("h"));
+    assert!(!automaton.match_whole("d"));
+    assert!(!automaton.match_whole("f"));
+    assert!(!automaton.match_whole("i"));
+}
+
+#[test]
+fn test_class_nested_operations_1() {
+    let automaton = Automaton::from_string("[^[a-z0-9&&[^x]]--[0-4]]")
+        .unwrap()
+        .into_min_dfa();
+    assert!(automaton.match_whole("x"));
+    assert!(automaton.match_whole("A"));
+    assert!(automaton.match_whole("0"));
+    assert!(!automaton.match_whole("5"));
+    assert!(!automaton.match_whole("a"));
+}
+
+#[test]
+fn test_perl_digit_1() {
+    let automaton = Automaton::from_string("\\d+").unwrap().into_min_dfa();
+    assert!(automaton.match_whole("0123456789"));
+    assert!(automaton.match_whole("٣٤"));
+    assert!(!automaton.match_whole("12a"));
+}
+
+#[test]
+fn test_perl_digit_negated_1() {
+    let automaton = Automaton::from_string("\\D").unwrap().into_min_dfa();
+    assert!(automaton.match_whole("a"));
+    assert!(automaton.match_whole("\n"));
+    assert!(!automaton.match_whole("1"));
+    assert!(!automaton.match_whole("٣"));
+}
+
+#[test]
+fn test_perl_word_1() {
+    let automaton = Automaton::from_string("\\w+").unwrap().into_min_dfa();
+    assert!(automaton.match_whole("snake_case_2"));
+    assert!(automaton.match_whole("äpple"));
+    assert!(automaton.match_whole("λόγος"));
+    assert!(!automaton.match_whole("kebab-case"));
+}
+
+#[test]
+fn test_perl_word_negated_1() {
+    let automaton = Automaton::from_string("\\W").unwrap().into_min_dfa();
+    assert!(automaton.match_whole("-"));
+    assert!(automaton.match_whole(" "));
+    assert!(!automaton.match_whole("_"));
+    assert!(!automaton.match_whole("ä"));
+}
+
+#[test]
+fn test_perl_space_1() {
+    let automaton = Automaton::from_string("a\\s+b").unwrap().into_min_dfa();
+    assert!(automaton.match_whole("a b"));
+    assert!(automaton.match_whole("a\t\r\n b"));
+    assert!(automaton.match_whole("a\u{3000}b"));
+    assert!(!automaton.match_whole("ab"));
+}
+
+#[test]
+fn test_perl_space_negated_1() {
+    let automaton = Automaton::from_string("\\S+").unwrap().into_min_dfa();
+    assert!(automaton.match_whole("word"));
+    assert!(!automaton.match_whole("two words"));
+    assert!(!automaton.match_whole("\u{3000}"));
+}
+
+#[test]
+fn test_perl_in_bracketed_class_1() {
+    let automaton = Automaton::from_string("[\\d\\s]+").unwrap().into_min_dfa();
+    assert!(automaton.match_whole("1 2 3"));
+    assert!(!automaton.match_whole("1, 2, 3"));
+
+    let automaton = Automaton::from_string("[^\\W\\d]+").unwrap().into_min_dfa();
+    assert!(automaton.match_whole("word_"));
+    assert!(!automaton.match_whole("word1"));
+}
+
+#[test]
+fn test_perl_ascii_1() {
+    let config = TranslatorConfig {
+        unicode: false,
+        ..TranslatorConfig::default()
+    };
+    let automaton = Automaton::from_string_with_config("\\w+\\s\\d", &config)
+        .unwrap()
+        .into_min_dfa();
+    assert!(automaton.match_whole("snake_case 2"));
+    assert!(!automaton.match_whole("äpple 2"));
+    assert!(!automaton.match_whole("snake_case\u{3000}2"));
+    assert!(!automaton.match_whole("snake_case ٣"));
+
+    // The ascii negation of a perl class contains bytes that are not valid utf-8
+    let error = Automaton::from_string_with_config("\\D", &config).unwrap_err();
+    assert!(matches!(error.kind(), TranslatorErrorKind::Unsupported(_)));
+}
+
+#[test]
+fn test_unicode_class_1() {
+    let automaton = Automaton::from_string("\\p{L}+").unwrap().into_min_dfa();
+    assert!(automaton.match_whole("word"));
+    assert!(automaton.match_whole("слово"));
+    assert!(automaton.match_whole("単語"));
+    assert!(!automaton.match_whole("word1"));
+}
+
+#[test]
+fn test_unicode_class_2() {
+    let automaton = Automaton::from_string("\\pL\\p{Nd}")
+        .unwrap()
+        .into_min_dfa();
+    assert!(automaton.match_whole("a1"));
+    assert!(automaton.match_whole("λ٣"));
+    assert!(!automaton.match_whole("1a"));
+}
+
+#[test]
+fn test_unicode_class_script_1() {
+    let automaton = Automaton::from_string("\\p{Greek}+")
+        .unwrap()
+        .into_min_dfa();
+    assert!(automaton.match_whole("λόγος"));
+    assert!(!automaton.match_whole("logos"));
+
+    let automaton = Automaton::from_string("\\p{Script=Latin}+")
+        .unwrap()
+        .into_min_dfa();
+    assert!(automaton.match_whole("logos"));
+    assert!(!automaton.match_whole("λόγος"));
+}
+
+#[test]
+fn test_unicode_class_negated_1() {
+    let automaton = Automaton::from_string("\\P{L}").unwrap().into_min_dfa();
+    assert!(automaton.match_whole("1"));
+    assert!(automaton.match_whole("\u{10FFFF}"));
+    assert!(!automaton.match_whole("a"));
+    assert!(!automaton.match_whole("λ"));
+
+    let automaton = Automaton::from_string("\\p{sc!=Greek}")
+        .unwrap()
+        .into_min_dfa();
+    assert!(automaton.match_whole("a"));
+    assert!(!automaton.match_whole("λ"));
+
+    let automaton = Automaton::from_string("\\P{sc!=Greek}")
+        .unwrap()
+        .into_min_dfa();
+    assert!(automaton.match_whole("λ"));
+    assert!(!automaton.match_whole("a"));
+}
+
+#[test]
+fn test_unicode_class_in_bracketed_class_1() {
+    let automaton = Automaton::from_string("[\\p{Greek}&&\\p{Lu}]")
+        .unwrap()
+        .into_min_dfa();
+    assert!(automaton.match_whole("Λ"));
+    assert!(!automaton.match_whole("λ"));
+    assert!(!automaton.match_whole("L"));
+}
+
+#[test]
+fn test_unicode_class_error() {
+    let error = Automaton::from_string("\\p{NotAProperty}").unwrap_err();
+    assert!(matches!(error.kind(), TranslatorErrorKind::Syntax(_)));
+    assert_eq!(error.category(), ErrorCategory::Syntax);
+}
+
 #[test]
 fn test_intersection_1() {
     println!("test_intersection_1:");
@@ -129,22 +499,1427 @@ fn test_intersection_1() {
     println!("{}", intersection.to_dot_format());
 }
 
+#[test]
+fn test_intersection_nfa_1() {
+    // An nfa is turned into a dfa first, since it can reach more than one state via the same string
+    let automaton1 = Automaton::from_string("a|ab").unwrap();
+    let automaton2 = Automaton::from_string("ab|b").unwrap();
+    let intersection = automaton1.intersection(&automaton2);
+    assert!(intersection.match_whole("ab"));
+    assert!(!intersection.match_whole("a"));
+    assert!(!intersection.match_whole("b"));
+
+    let intersection = automaton2.intersection(&automaton1.into_min_dfa());
+    assert!(intersection.match_whole("ab"));
+    assert!(!intersection.match_whole(""));
+}
+
 #[test]
 fn test_parser_error() {
     println!("test_parser_error:");
-    let translator_result = Automaton::from_string("aa(");
-    assert!(match translator_result {
-        Err(TranslatorError::ParserError(_)) => true,
-        _ => false,
-    });
-}
-
-#[test]
-fn test_unsupported_error() {
-    println!("test_unsupported_error:");
-    let translator_result = Automaton::from_string("[:alnum:]");
-    assert!(match translator_result {
-        Err(TranslatorError::UnsupportedClassSetItem(_)) => true,
-        _ => false,
-    });
-}
\ No newline at end of file
+    let error = Automaton::from_string("aa(").unwrap_err();
+    assert!(matches!(error.kind(), TranslatorErrorKind::Syntax(_)));
+    assert_eq!(error.category(), ErrorCategory::Syntax);
+}
+
+#[test]
+fn test_translate_error() {
+    println!("test_translate_error:");
+    let error = Automaton::from_string("(?-u)\\p{Greek}").unwrap_err();
+    assert!(matches!(error.kind(), TranslatorErrorKind::Unsupported(_)));
+    assert_eq!(error.category(), ErrorCategory::Unsupported);
+}
+
+#[test]
+fn test_counted_repetition_exactly_1() {
+    let automaton = Automaton::from_string("a{3}").unwrap().into_min_dfa();
+    assert!(!automaton.match_whole("aa"));
+    assert!(automaton.match_whole("aaa"));
+    assert!(!automaton.match_whole("aaaa"));
+}
+
+#[test]
+fn test_counted_repetition_at_least_1() {
+    let automaton = Automaton::from_string("(ab){2,}").unwrap().into_min_dfa();
+    assert!(!automaton.match_whole("ab"));
+    assert!(automaton.match_whole("abab"));
+    assert!(automaton.match_whole("ababab"));
+    assert!(!automaton.match_whole("ababa"));
+}
+
+#[test]
+fn test_counted_repetition_bounded_1() {
+    let automaton = Automaton::from_string("[a-z]{3,8}").unwrap().into_min_dfa();
+    assert!(!automaton.match_whole("ab"));
+    assert!(automaton.match_whole("abc"));
+    assert!(automaton.match_whole("abcdefgh"));
+    assert!(!automaton.match_whole("abcdefghi"));
+}
+
+#[test]
+fn test_counted_repetition_bounded_2() {
+    let automaton = Automaton::from_string("a{0,2}b{0}").unwrap().into_min_dfa();
+    assert!(automaton.match_whole(""));
+    assert!(automaton.match_whole("a"));
+    assert!(automaton.match_whole("aa"));
+    assert!(!automaton.match_whole("aaa"));
+    assert!(!automaton.match_whole("b"));
+}
+
+#[test]
+fn test_repetition_limit_error() {
+    let config = TranslatorConfig {
+        repetition_limit: 10,
+        ..TranslatorConfig::default()
+    };
+    assert!(Automaton::from_string_with_config("a{10}", &config).is_ok());
+    let error = Automaton::from_string_with_config("a{2,11}", &config).unwrap_err();
+    assert!(matches!(
+        error.kind(),
+        TranslatorErrorKind::RepetitionLimitExceeded(10)
+    ));
+    assert_eq!(error.category(), ErrorCategory::ResourceLimit);
+}
+
+#[test]
+fn test_repetition_limit_nested() {
+    let config = TranslatorConfig {
+        repetition_limit: 100,
+        ..TranslatorConfig::default()
+    };
+    assert!(Automaton::from_string_with_config("(a{10}){10}b{100}", &config).is_ok());
+    assert!(Automaton::from_string_with_config("(a{0}){100}", &config).is_ok());
+    let error = Automaton::from_string_with_config("(a{10}){11}", &config).unwrap_err();
+    assert!(matches!(
+        error.kind(),
+        TranslatorErrorKind::RepetitionLimitExceeded(100)
+    ));
+    assert_eq!(error.category(), ErrorCategory::ResourceLimit);
+    assert_eq!(error.span().start, 1);
+
+    // Nested repetitions would otherwise build a billion copies of ```a```
+    let error = Automaton::from_string("((a{1000}){1000}){1000}").unwrap_err();
+    assert_eq!(error.category(), ErrorCategory::ResourceLimit);
+    let error = Automaton::from_string("(?:(?:a{20,}){60})*").unwrap_err();
+    assert_eq!(error.category(), ErrorCategory::ResourceLimit);
+}
+
+#[test]
+fn test_dot_1() {
+    let automaton = Automaton::from_string("a.c").unwrap().into_min_dfa();
+    assert!(automaton.match_whole("abc"));
+    assert!(automaton.match_whole("a.c"));
+    assert!(automaton.match_whole("aλc"));
+    assert!(automaton.match_whole("a\u{10FFFF}c"));
+    assert!(!automaton.match_whole("a\nc"));
+    assert!(!automaton.match_whole("ac"));
+    assert!(!automaton.match_whole("abbc"));
+}
+
+#[test]
+fn test_dot_2() {
+    let automaton = Automaton::from_string(".*").unwrap().into_min_dfa();
+    assert!(automaton.match_whole(""));
+    assert!(automaton.match_whole("any line at all"));
+    assert!(!automaton.match_whole("two\nlines"));
+}
+
+#[test]
+fn test_dot_matches_new_line_flag_1() {
+    let automaton = Automaton::from_string("(?s)a.c").unwrap().into_min_dfa();
+    assert!(automaton.match_whole("abc"));
+    assert!(automaton.match_whole("a\nc"));
+}
+
+#[test]
+fn test_dot_matches_new_line_flag_2() {
+    // The flag only applies inside the group
+    let automaton = Automaton::from_string("(?s:.).").unwrap().into_min_dfa();
+    assert!(automaton.match_whole("\na"));
+    assert!(!automaton.match_whole("a\n"));
+
+    // The flag applies to the rest of the group it is set in, including later alternatives
+    let automaton = Automaton::from_string("(a(?s).|.)").unwrap().into_min_dfa();
+    assert!(automaton.match_whole("a\n"));
+    assert!(automaton.match_whole("\n"));
+
+    let automaton = Automaton::from_string("(a(?s).)|.").unwrap().into_min_dfa();
+    assert!(automaton.match_whole("a\n"));
+    assert!(!automaton.match_whole("\n"));
+}
+
+#[test]
+fn test_dot_matches_new_line_config_1() {
+    let config = TranslatorConfig {
+        dot_matches_new_line: true,
+        ..TranslatorConfig::default()
+    };
+    let automaton = Automaton::from_string_with_config(".(?-s).", &config)
+        .unwrap()
+        .into_min_dfa();
+    assert!(automaton.match_whole("\na"));
+    assert!(!automaton.match_whole("a\n"));
+}
+
+#[test]
+fn test_case_insensitive_flag_1() {
+    let automaton = Automaton::from_string("(?i)abc").unwrap().into_min_dfa();
+    assert!(automaton.match_whole("abc"));
+    assert!(automaton.match_whole("ABC"));
+    assert!(automaton.match_whole("aBc"));
+    assert!(!automaton.match_whole("abd"));
+}
+
+#[test]
+fn test_case_insensitive_flag_2() {
+    // Simple case folding includes the kelvin sign and the greek final sigma
+    let automaton = Automaton::from_string("(?i)kσ").unwrap().into_min_dfa();
+    assert!(automaton.match_whole("KΣ"));
+    assert!(automaton.match_whole("\u{212A}ς"));
+    assert!(!automaton.match_whole("kς\u{212A}"));
+}
+
+#[test]
+fn test_case_insensitive_flag_classes_1() {
+    let automaton = Automaton::from_string("(?i)[a-c]+").unwrap().into_min_dfa();
+    assert!(automaton.match_whole("aBC"));
+    assert!(!automaton.match_whole("D"));
+
+    // Case folding is applied before negation
+    let automaton = Automaton::from_string("(?i)[^x]").unwrap().into_min_dfa();
+    assert!(automaton.match_whole("y"));
+    assert!(!automaton.match_whole("x"));
+    assert!(!automaton.match_whole("X"));
+
+    let automaton = Automaton::from_string("(?i)[a-z--k]")
+        .unwrap()
+        .into_min_dfa();
+    assert!(automaton.match_whole("A"));
+    assert!(!automaton.match_whole("K"));
+    assert!(!automaton.match_whole("\u{212A}"));
+
+    let automaton = Automaton::from_string("(?i)\\p{Lu}[[:upper:]]")
+        .unwrap()
+        .into_min_dfa();
+    assert!(automaton.match_whole("λa"));
+}
+
+#[test]
+fn test_case_insensitive_flag_scope_1() {
+    let automaton = Automaton::from_string("(?i)a(?-i:b)c")
+        .unwrap()
+        .into_min_dfa();
+    assert!(automaton.match_whole("AbC"));
+    assert!(!automaton.match_whole("ABC"));
+
+    let automaton = Automaton::from_string("a(?i:b)c").unwrap().into_min_dfa();
+    assert!(automaton.match_whole("aBc"));
+    assert!(!automaton.match_whole("aBC"));
+
+    let automaton = Automaton::from_string("(a(?i)b)b").unwrap().into_min_dfa();
+    assert!(automaton.match_whole("aBb"));
+    assert!(!automaton.match_whole("aBB"));
+}
+
+#[test]
+fn test_case_insensitive_flag_ascii_1() {
+    let automaton = Automaton::from_string("(?i-u)k\\w").unwrap().into_min_dfa();
+    assert!(automaton.match_whole("Ka"));
+    assert!(!automaton.match_whole("\u{212A}a"));
+    assert!(!automaton.match_whole("kλ"));
+}
+
+#[test]
+fn test_case_insensitive_config_1() {
+    let config = TranslatorConfig {
+        case_insensitive: true,
+        ..TranslatorConfig::default()
+    };
+    let automaton = Automaton::from_string_with_config("ab(?-i)c", &config)
+        .unwrap()
+        .into_min_dfa();
+    assert!(automaton.match_whole("ABc"));
+    assert!(!automaton.match_whole("ABC"));
+}
+
+#[test]
+fn test_ignore_whitespace_flag_1() {
+    let automaton = Automaton::from_string("(?x) a b+ # any number of b\n c")
+        .unwrap()
+        .into_min_dfa();
+    assert!(automaton.match_whole("abbc"));
+    assert!(!automaton.match_whole("a b c"));
+
+    let automaton = Automaton::from_string("(?x: a b ) c")
+        .unwrap()
+        .into_min_dfa();
+    assert!(automaton.match_whole("ab c"));
+    assert!(!automaton.match_whole("abc"));
+}
+
+#[test]
+fn test_ignore_whitespace_config_1() {
+    let config = TranslatorConfig {
+        ignore_whitespace: true,
+        ..TranslatorConfig::default()
+    };
+    let automaton = Automaton::from_string_with_config("a b \\  c", &config)
+        .unwrap()
+        .into_min_dfa();
+    assert!(automaton.match_whole("ab c"));
+    assert!(!automaton.match_whole("a b c"));
+}
+
+#[test]
+fn test_swap_greed_flag_1() {
+    let automaton = Automaton::from_string("(?U)a+b??").unwrap().into_min_dfa();
+    assert!(automaton.match_whole("aaa"));
+    assert!(automaton.match_whole("aab"));
+    assert!(!automaton.match_whole("b"));
+}
+
+#[test]
+fn test_anchor_start_1() {
+    let automaton = Automaton::from_string("^ab").unwrap().into_min_dfa();
+    assert!(automaton.match_whole("ab"));
+    assert!(!automaton.match_whole("aab"));
+    assert_eq!(automaton.match_substrings("abab"), vec![(0..2)]);
+}
+
+#[test]
+fn test_anchor_end_1() {
+    let automaton = Automaton::from_string("ab$").unwrap().into_min_dfa();
+    assert!(automaton.match_whole("ab"));
+    assert_eq!(automaton.match_substrings("abab"), vec![(2..4)]);
+    assert_eq!(automaton.match_longest_prefix("abab"), None);
+}
+
+#[test]
+fn test_anchor_text_1() {
+    let automaton = Automaton::from_string("\\Aa+\\z").unwrap().into_min_dfa();
+    assert!(automaton.match_whole("aa"));
+    assert_eq!(automaton.match_substrings("aa"), vec![(0..2)]);
+    assert_eq!(automaton.match_substrings("aab"), vec![]);
+}
+
+#[test]
+fn test_anchor_unsatisfiable_1() {
+    let automaton = Automaton::from_string("a^b").unwrap().into_min_dfa();
+    assert!(!automaton.match_whole("ab"));
+    assert_eq!(automaton.match_substrings("ab"), vec![]);
+}
+
+#[test]
+fn test_anchor_multi_line_1() {
+    let automaton = Automaton::from_string("(?m)^a+$").unwrap().into_min_dfa();
+    assert!(automaton.match_whole("aa"));
+    assert_eq!(automaton.match_substrings("aa\na"), vec![(0..2), (3..4)]);
+    let automaton = Automaton::from_string("^a+$").unwrap().into_min_dfa();
+    assert_eq!(automaton.match_substrings("aa\na"), vec![]);
+}
+
+#[test]
+fn test_anchor_multi_line_config_1() {
+    let config = TranslatorConfig {
+        multi_line: true,
+        ..TranslatorConfig::default()
+    };
+    let automaton = Automaton::from_string_with_config("^b", &config)
+        .unwrap()
+        .into_min_dfa();
+    assert_eq!(automaton.match_substrings("ab\nb"), vec![(3..4)]);
+}
+
+#[test]
+fn test_anchor_intersection_1() {
+    let automaton1 = Automaton::from_string("^a").unwrap().into_min_dfa();
+    let automaton2 = Automaton::from_string("a").unwrap().into_min_dfa();
+    let intersection = automaton1.intersection(&automaton2).into_min_dfa();
+    assert!(intersection.match_whole("a"));
+    assert_eq!(intersection.match_substrings("aa"), vec![(0..1)]);
+}
+
+#[test]
+fn test_word_boundary_1() {
+    let automaton = Automaton::from_string("\\bcat\\b").unwrap().into_min_dfa();
+    assert!(automaton.match_whole("cat"));
+    assert_eq!(
+        automaton.match_substrings("cat concat cats cat"),
+        vec![(0..3), (16..19)]
+    );
+}
+
+#[test]
+fn test_word_boundary_2() {
+    let automaton = Automaton::from_string("a+\\b").unwrap().into_min_dfa();
+    assert_eq!(automaton.match_first_prefix("aa b"), Some("aa"));
+    assert_eq!(automaton.match_longest_prefix("aa b"), Some(0..2));
+    assert_eq!(automaton.match_first_prefix("aab"), None);
+    assert_eq!(automaton.match_longest_substring("aab a"), Some(4..5));
+}
+
+#[test]
+fn test_not_word_boundary_1() {
+    let automaton = Automaton::from_string("\\Bcat").unwrap().into_min_dfa();
+    assert!(!automaton.match_whole("cat"));
+    assert_eq!(automaton.match_substrings("cat concat"), vec![(7..10)]);
+}
+
+#[test]
+fn test_word_boundary_unicode_1() {
+    let automaton = Automaton::from_string("\\bx").unwrap().into_min_dfa();
+    assert_eq!(automaton.match_substrings("éx"), vec![]);
+    assert_eq!(automaton.match_substrings("-x"), vec![(1..2)]);
+    let automaton = Automaton::from_string("(?-u:\\b)x").unwrap().into_min_dfa();
+    assert_eq!(automaton.match_substrings("éx"), vec![(2..3)]);
+    assert_eq!(automaton.match_substrings("ax"), vec![]);
+}
+
+#[test]
+fn test_literal_verbatim_1() {
+    let automaton = Automaton::from_string("a☃😀").unwrap().into_min_dfa();
+    assert!(automaton.match_whole("a☃😀"));
+    assert!(!automaton.match_whole("a☃"));
+}
+
+#[test]
+fn test_literal_punctuation_1() {
+    let automaton = Automaton::from_string(r"\\\.\+\*\?\(\)\|\[\]\{\}\^\$\#\&\-\~")
+        .unwrap()
+        .into_min_dfa();
+    assert!(automaton.match_whole(r"\.+*?()|[]{}^$#&-~"));
+    assert!(!automaton.match_whole(r"\a+*?()|[]{}^$#&-~"));
+}
+
+#[test]
+fn test_literal_punctuation_2() {
+    let automaton = Automaton::from_string(r"a\.b").unwrap().into_min_dfa();
+    assert!(automaton.match_whole("a.b"));
+    assert!(!automaton.match_whole("axb"));
+}
+
+#[test]
+fn test_literal_octal_1() {
+    let config = TranslatorConfig {
+        octal: true,
+        ..TranslatorConfig::default()
+    };
+    let automaton = Automaton::from_string_with_config(r"\141\0", &config)
+        .unwrap()
+        .into_min_dfa();
+    assert!(automaton.match_whole("a\0"));
+    assert!(!automaton.match_whole("a0"));
+    let error = Automaton::from_string(r"\141").unwrap_err();
+    assert!(matches!(error.kind(), TranslatorErrorKind::Unsupported(_)));
+    assert_eq!(error.category(), ErrorCategory::Unsupported);
+}
+
+#[test]
+fn test_literal_hex_fixed_1() {
+    let automaton = Automaton::from_string(r"\x41\u00E9\U0001F600")
+        .unwrap()
+        .into_min_dfa();
+    assert!(automaton.match_whole("Aé😀"));
+    assert!(!automaton.match_whole("aé😀"));
+}
+
+#[test]
+fn test_literal_hex_brace_1() {
+    let automaton = Automaton::from_string(r"\x{41}\u{e9}\U{1F600}")
+        .unwrap()
+        .into_min_dfa();
+    assert!(automaton.match_whole("Aé😀"));
+    assert!(!automaton.match_whole("A\u{e9}\u{1F601}"));
+}
+
+#[test]
+fn test_literal_hex_brace_2() {
+    let error = Automaton::from_string(r"\u{D800}").unwrap_err();
+    assert!(matches!(error.kind(), TranslatorErrorKind::Syntax(_)));
+    assert_eq!(error.category(), ErrorCategory::Syntax);
+}
+
+#[test]
+fn test_literal_special_1() {
+    let automaton = Automaton::from_string(r"\a\f\t\n\r\v")
+        .unwrap()
+        .into_min_dfa();
+    assert!(automaton.match_whole("\x07\x0C\t\n\r\x0B"));
+    assert!(!automaton.match_whole(r"\a\f\t\n\r\v"));
+}
+
+#[test]
+fn test_literal_special_2() {
+    let automaton = Automaton::from_string(r"(?x)a\ b").unwrap().into_min_dfa();
+    assert!(automaton.match_whole("a b"));
+    assert!(!automaton.match_whole("ab"));
+}
+
+#[test]
+fn test_literal_escape_case_insensitive_1() {
+    let automaton = Automaton::from_string(r"(?i)\x41\u{e9}")
+        .unwrap()
+        .into_min_dfa();
+    assert!(automaton.match_whole("aÉ"));
+    assert!(automaton.match_whole("Aé"));
+}
+
+#[test]
+fn test_literal_escape_class_1() {
+    let automaton = Automaton::from_string(r"[\x41-\x43\n\.]+")
+        .unwrap()
+        .into_min_dfa();
+    assert!(automaton.match_whole("ABC\n."));
+    assert!(!automaton.match_whole("D"));
+    assert!(!automaton.match_whole("x"));
+}
+
+#[test]
+fn test_class_bytes_1() {
+    let automaton = Automaton::from_string("(?-u)[a-c]\\x41")
+        .unwrap()
+        .into_min_dfa();
+    assert!(automaton.match_whole("bA"));
+    assert!(!automaton.match_whole("dA"));
+    assert!(!automaton.match_whole("b\u{C1}"));
+
+    // Bytes that are not ascii are not valid utf-8 on their own
+    for pattern in ["(?-u)[^a]", "(?-u)\\xE9"] {
+        let error = Automaton::from_string(pattern).unwrap_err();
+        assert!(
+            matches!(error.kind(), TranslatorErrorKind::Unsupported(_)),
+            "{}",
+            pattern
+        );
+        assert_eq!(error.category(), ErrorCategory::Unsupported, "{}", pattern);
+    }
+}
+
+#[test]
+fn test_error_span_1() {
+    let error = Automaton::from_string("ab{1001}").unwrap_err();
+    assert_eq!(error.pattern(), "ab{1001}");
+    assert_eq!(error.span().start, 1);
+    assert_eq!(error.span().end, 8);
+    assert_eq!(
+        error.to_string(),
+        "error when translating regular expression: resource limit exceeded: \
+         repetition exceeds the repetition limit of 1000\n    ab{1001}\n     ^^^^^^^"
+    );
+}
+
+#[test]
+fn test_error_span_2() {
+    let error = Automaton::from_string("λ(a\nb").unwrap_err();
+    assert_eq!(error.category(), ErrorCategory::Syntax);
+    assert_eq!(
+        error.to_string(),
+        "error when translating regular expression: syntax error: unclosed group\n    λ(a\n     ^"
+    );
+}
+
+#[test]
+fn test_error_kind_nest_limit_1() {
+    let pattern = format!("{}a{}", "(".repeat(300), ")".repeat(300));
+    let error = Automaton::from_string(&pattern).unwrap_err();
+    assert!(matches!(
+        error.kind(),
+        TranslatorErrorKind::NestLimitExceeded(_)
+    ));
+    assert_eq!(error.category(), ErrorCategory::ResourceLimit);
+    assert!(error.to_string().contains("nesting exceeds the nest limit"));
+}
+
+#[test]
+fn test_error_unsupported_1() {
+    let error = Automaton::from_string("(a)\\1").unwrap_err();
+    assert_eq!(error.category(), ErrorCategory::Unsupported);
+    assert!(error.to_string().ends_with("    (a)\\1\n       ^^"));
+
+    let error = Automaton::from_string("a(?=b)").unwrap_err();
+    assert_eq!(error.category(), ErrorCategory::Unsupported);
+}
+
+#[test]
+fn test_no_panic_1() {
+    for pattern in &[
+        "a||b",
+        "(|a)+",
+        "((a|)|(|b))*c?",
+        "(?i)(a(?-i)b|)*",
+        "(a{0}|b{0,0})",
+        "^*$+",
+        "\\b*",
+    ] {
+        let automaton = Automaton::from_string(pattern).unwrap().into_min_dfa();
+        assert!(!automaton.match_whole("x"));
+    }
+}
+
+#[test]
+fn test_invalid_transition_1() {
+    let mut automaton = Automaton::new();
+    let state = automaton.add_state();
+    automaton.set_start_state(state);
+    automaton.set_accepting(state, true);
+    automaton.add_transition(state, state + 1, Some('a'));
+    automaton.add_transition(state + 1, state, Some('b'));
+    let automaton = automaton.into_min_dfa();
+    assert!(automaton.match_whole(""));
+    assert!(!automaton.match_whole("a"));
+}
+
+#[test]
+fn test_reversed_range_transition_1() {
+    // A reversed range does not contain any symbol, so it adds no transition
+    let mut automaton = Automaton::new();
+    let start_state = automaton.add_state();
+    let end_state = automaton.add_state();
+    automaton.set_start_state(start_state);
+    automaton.set_accepting(end_state, true);
+    automaton.add_range_transition(start_state, end_state, CharRange::new('z', 'a'));
+    assert!(automaton.is_empty());
+    let automaton = automaton.into_min_dfa();
+    for input in ["a", "m", "z", "{", "\u{10FFFF}"] {
+        assert!(!automaton.match_whole(input), "{}", input);
+    }
+}
+
+#[test]
+fn test_dfa_limits_1() {
+    let automaton = Automaton::from_string("(a|b)*a(a|b){20}").unwrap();
+    let limits = DfaLimits {
+        max_states: 1000,
+        ..DfaLimits::default()
+    };
+    let dfa_result = automaton.into_min_dfa_with_limits(&limits);
+    assert_eq!(dfa_result.unwrap_err(), LimitError::StatesExceeded(1000));
+}
+
+#[test]
+fn test_dfa_limits_2() {
+    let automaton = Automaton::from_string("(a|b)*a(a|b){2}")
+        .unwrap()
+        .into_min_dfa_with_limits(&DfaLimits::default())
+        .unwrap();
+    assert!(automaton.match_whole("aaba"));
+    assert!(!automaton.match_whole("abaa"));
+}
+
+#[test]
+fn test_dfa_limits_3() {
+    let automaton = Automaton::from_string("abcde").unwrap();
+    let limits = DfaLimits {
+        max_states: 3,
+        ..DfaLimits::default()
+    };
+    let dfa_result = automaton.clone().into_dfa_with_limits(&limits);
+    assert_eq!(dfa_result.unwrap_err(), LimitError::StatesExceeded(3));
+
+    let limits = DfaLimits {
+        max_transitions: 2,
+        ..DfaLimits::default()
+    };
+    let dfa_result = automaton.clone().into_dfa_with_limits(&limits);
+    assert_eq!(dfa_result.unwrap_err(), LimitError::TransitionsExceeded(2));
+
+    let limits = DfaLimits {
+        max_bytes: 100,
+        ..DfaLimits::default()
+    };
+    let dfa_result = automaton.into_min_dfa_with_limits(&limits);
+    assert_eq!(dfa_result.unwrap_err(), LimitError::BytesExceeded(100));
+}
+
+#[test]
+fn test_dfa_limits_captures_1() {
+    // The dfa that finds the captures does not count towards the limits of the dfa
+    let limits = DfaLimits {
+        max_states: 33,
+        ..DfaLimits::default()
+    };
+    let dfa = Automaton::from_string("(a|b)*a(a|b){4}")
+        .unwrap()
+        .into_dfa_with_limits(&limits)
+        .unwrap();
+    assert_eq!(dfa.states, 33);
+    assert!(dfa.match_whole("babbbb"));
+    let captures = dfa.try_captures("babbbb").unwrap().unwrap();
+    assert_eq!(captures.get(1), Some(0..1));
+    assert_eq!(captures.get(2), Some(5..6));
+    assert_eq!(dfa.try_captures("bbbbb"), Ok(None));
+}
+
+#[test]
+fn test_minimize_many_states_1() {
+    // Minimizing a dfa with many states that are all distinguishable keeps every state
+    let dfa = Automaton::from_string("(?:a|b)*a(?:a|b){8}")
+        .unwrap()
+        .into_dfa_with_limits(&DfaLimits::default())
+        .unwrap();
+    assert!(dfa.states >= 512);
+    let min_dfa = dfa.into_min_dfa_with_limits(&DfaLimits::default()).unwrap();
+    assert_eq!(min_dfa.states, 512);
+    assert!(min_dfa.match_whole("ababababa"));
+    assert!(!min_dfa.match_whole("abababab"));
+}
+
+#[test]
+fn test_minimize_large_classes_1() {
+    // Large unicode classes split the alphabet into many symbols
+    let min_dfa = Automaton::from_string(r"(?:\w+\.){6}\w+")
+        .unwrap()
+        .into_min_dfa_with_limits(&DfaLimits::default())
+        .unwrap();
+    assert_eq!(min_dfa.states, 14);
+    assert!(min_dfa.match_whole("a.b.c.d.e.f.gé"));
+    assert!(!min_dfa.match_whole("a.b.c.d.e.f"));
+}
+
+#[test]
+fn test_empty_1() {
+    let automaton = Automaton::from_string("").unwrap().into_min_dfa();
+    assert!(automaton.match_whole(""));
+    assert!(!automaton.match_whole("a"));
+    assert_eq!(automaton.match_substrings("ab"), vec![(0..0), (1..1)]);
+}
+
+#[test]
+fn test_empty_alternation_1() {
+    let automaton = Automaton::from_string("a|").unwrap().into_min_dfa();
+    assert!(automaton.match_whole(""));
+    assert!(automaton.match_whole("a"));
+    assert!(!automaton.match_whole("aa"));
+
+    let automaton = Automaton::from_string("|a").unwrap().into_min_dfa();
+    assert!(automaton.match_whole(""));
+    assert!(automaton.match_whole("a"));
+}
+
+#[test]
+fn test_empty_group_1() {
+    let automaton = Automaton::from_string("()").unwrap().into_min_dfa();
+    assert!(automaton.match_whole(""));
+    assert!(!automaton.match_whole("a"));
+
+    let automaton = Automaton::from_string("a()b(?:)").unwrap().into_min_dfa();
+    assert!(automaton.match_whole("ab"));
+    assert!(!automaton.match_whole("a"));
+}
+
+#[test]
+fn test_empty_group_alternation_1() {
+    let automaton = Automaton::from_string("(|b)").unwrap().into_min_dfa();
+    assert!(automaton.match_whole(""));
+    assert!(automaton.match_whole("b"));
+    assert!(!automaton.match_whole("bb"));
+
+    let automaton = Automaton::from_string("a(|b)c").unwrap().into_min_dfa();
+    assert!(automaton.match_whole("ac"));
+    assert!(automaton.match_whole("abc"));
+    assert!(!automaton.match_whole("ab"));
+}
+
+#[test]
+fn test_empty_repetition_1() {
+    let automaton = Automaton::from_string("()*a(|b)+").unwrap().into_min_dfa();
+    assert!(automaton.match_whole("a"));
+    assert!(automaton.match_whole("abb"));
+    assert!(!automaton.match_whole(""));
+}
+
+#[test]
+fn test_captures_1() {
+    let automaton = Automaton::from_string("(a+)(b*)c").unwrap().into_min_dfa();
+    let captures = automaton.captures("aabbc").unwrap();
+    assert_eq!(captures.len(), 3);
+    assert_eq!(captures.get(0), Some(0..5));
+    assert_eq!(captures.get(1), Some(0..2));
+    assert_eq!(captures.get(2), Some(2..4));
+    assert_eq!(captures.get(3), None);
+    assert!(automaton.captures("aabb").is_none());
+}
+
+#[test]
+fn test_captures_name_1() {
+    let automaton = Automaton::from_string(r"(?P<year>\d{4})-(?P<month>\d{2})")
+        .unwrap()
+        .into_min_dfa();
+    let captures = automaton.captures("2024-05").unwrap();
+    assert_eq!(captures.name("year"), Some(0..4));
+    assert_eq!(captures.name("month"), Some(5..7));
+    assert_eq!(captures.get(2), Some(5..7));
+    assert_eq!(captures.name("day"), None);
+}
+
+#[test]
+fn test_captures_alternation_1() {
+    // The first alternative that leads to a match is preferred
+    let automaton = Automaton::from_string("(a|ab)(c|bcd)(d*)")
+        .unwrap()
+        .into_min_dfa();
+    let captures = automaton.captures("abcd").unwrap();
+    assert_eq!(captures.get(1), Some(0..1));
+    assert_eq!(captures.get(2), Some(1..4));
+    assert_eq!(captures.get(3), Some(4..4));
+}
+
+#[test]
+fn test_captures_greedy_1() {
+    let automaton = Automaton::from_string("(a*)(a*)").unwrap().into_min_dfa();
+    let captures = automaton.captures("aaa").unwrap();
+    assert_eq!(captures.get(1), Some(0..3));
+    assert_eq!(captures.get(2), Some(3..3));
+
+    let automaton = Automaton::from_string("(a*?)(a*)").unwrap().into_min_dfa();
+    let captures = automaton.captures("aaa").unwrap();
+    assert_eq!(captures.get(1), Some(0..0));
+    assert_eq!(captures.get(2), Some(0..3));
+
+    let automaton = Automaton::from_string("(a+?)(a*)").unwrap().into_min_dfa();
+    let captures = automaton.captures("aaa").unwrap();
+    assert_eq!(captures.get(1), Some(0..1));
+    assert_eq!(captures.get(2), Some(1..3));
+}
+
+#[test]
+fn test_captures_repetition_1() {
+    // A repeated group captures its last iteration
+    let automaton = Automaton::from_string("(a|b)*").unwrap().into_min_dfa();
+    let captures = automaton.captures("aab").unwrap();
+    assert_eq!(captures.get(1), Some(2..3));
+    assert_eq!(automaton.captures("").unwrap().get(1), None);
+
+    let automaton = Automaton::from_string("(ab){2}").unwrap().into_min_dfa();
+    assert_eq!(automaton.captures("abab").unwrap().get(1), Some(2..4));
+}
+
+#[test]
+fn test_captures_unmatched_1() {
+    let automaton = Automaton::from_string("(a)|(b)").unwrap().into_min_dfa();
+    let captures = automaton.captures("b").unwrap();
+    assert_eq!(captures.get(1), None);
+    assert_eq!(captures.get(2), Some(0..1));
+}
+
+#[test]
+fn test_captures_nested_1() {
+    let automaton = Automaton::from_string("((a)(?:b(c))?)d").unwrap();
+    let captures = automaton.captures("abcd").unwrap();
+    assert_eq!(captures.get(1), Some(0..3));
+    assert_eq!(captures.get(2), Some(0..1));
+    assert_eq!(captures.get(3), Some(2..3));
+
+    let captures = automaton.captures("ad").unwrap();
+    assert_eq!(captures.get(1), Some(0..1));
+    assert_eq!(captures.get(3), None);
+}
+
+#[test]
+fn test_captures_look_1() {
+    let automaton = Automaton::from_string(r"(?-u)(\w+)\b([ ,]*)")
+        .unwrap()
+        .into_min_dfa();
+    let captures = automaton.captures("word, ").unwrap();
+    assert_eq!(captures.get(1), Some(0..4));
+    assert_eq!(captures.get(2), Some(4..6));
+
+    let automaton = Automaton::from_string(r"(?m)(a*)$\n(b)")
+        .unwrap()
+        .into_min_dfa();
+    let captures = automaton.captures("aa\nb").unwrap();
+    assert_eq!(captures.get(1), Some(0..2));
+    assert_eq!(captures.get(2), Some(3..4));
+}
+
+#[test]
+fn test_captures_unicode_class_1() {
+    // Large classes are stepped through as ranges, not one symbol at a time
+    let automaton = Automaton::from_string(r"(\w+)\b(?:\s|\p{P})+(?P<rest>\w*)")
+        .unwrap()
+        .into_min_dfa();
+    let captures = automaton.captures("naïve, слово").unwrap();
+    assert_eq!(captures.get(1), Some(0..6));
+    assert_eq!(captures.name("rest"), Some(8..18));
+    assert!(automaton.captures("naïve слово!").is_none());
+}
+
+#[test]
+fn test_captures_without_groups_1() {
+    let automaton = Automaton::from_string("ab").unwrap().into_min_dfa();
+    let captures = automaton.captures("ab").unwrap();
+    assert_eq!(captures.len(), 1);
+    assert_eq!(captures.get(0), Some(0..2));
+    assert!(automaton.captures("a").is_none());
+}
+
+fn leftmost_first(pattern: &str) -> Automaton {
+    let config = TranslatorConfig {
+        match_kind: MatchKind::LeftmostFirst,
+        ..TranslatorConfig::default()
+    };
+    Automaton::from_string_with_config(pattern, &config)
+        .unwrap()
+        .into_min_dfa()
+}
+
+#[test]
+fn test_match_kind_lazy_1() {
+    let automaton = leftmost_first("a+?");
+    assert_eq!(automaton.match_prefix("aaa"), Some(0..1));
+    let automaton = leftmost_first("a+");
+    assert_eq!(automaton.match_prefix("aaa"), Some(0..3));
+    let automaton = leftmost_first("a*?");
+    assert_eq!(automaton.match_prefix("aaa"), Some(0..0));
+    let automaton = leftmost_first("a{2,4}?b?");
+    assert_eq!(automaton.match_prefix("aaaab"), Some(0..2));
+
+    // Lazy repetitions still match as much as they need to
+    let automaton = leftmost_first("a+?b");
+    assert_eq!(automaton.match_prefix("aaab"), Some(0..4));
+    assert_eq!(automaton.match_prefix("aaa"), None);
+}
+
+#[test]
+fn test_match_kind_alternation_1() {
+    let automaton = leftmost_first("a|ab");
+    assert_eq!(automaton.match_prefix("abc"), Some(0..1));
+    let automaton = leftmost_first("ab|a");
+    assert_eq!(automaton.match_prefix("abc"), Some(0..2));
+    let automaton = leftmost_first("(?:sam|samwise)e?");
+    assert_eq!(automaton.match_prefix("samwise"), Some(0..3));
+}
+
+#[test]
+fn test_match_kind_leftmost_1() {
+    let automaton = leftmost_first("b+?|ab");
+    assert_eq!(automaton.match_leftmost("xxabbb"), Some(2..4));
+    assert_eq!(automaton.match_leftmost("xxbbb"), Some(2..3));
+    assert_eq!(automaton.match_leftmost("xx"), None);
+
+    let automaton = leftmost_first(r"(?-u)\b\w+?\b");
+    assert_eq!(automaton.match_leftmost("  word word"), Some(2..6));
+}
+
+#[test]
+fn test_match_kind_longest_1() {
+    // The default kind finds the longest match, regardless of greed and order
+    let automaton = Automaton::from_string("a+?|ab").unwrap().into_min_dfa();
+    assert_eq!(automaton.match_kind(), MatchKind::LeftmostLongest);
+    assert_eq!(automaton.match_prefix("aab"), Some(0..2));
+    assert_eq!(automaton.match_leftmost("xab"), Some(1..3));
+}
+
+#[test]
+fn test_match_kind_nfa_1() {
+    let mut automaton = Automaton::from_string("a*?(a|b)").unwrap();
+    automaton.set_match_kind(MatchKind::LeftmostFirst);
+    assert_eq!(automaton.match_prefix("aab"), Some(0..1));
+    assert_eq!(automaton.clone().into_dfa().match_prefix("aab"), Some(0..1));
+    assert_eq!(automaton.into_min_dfa().match_prefix("bab"), Some(0..1));
+}
+
+#[test]
+fn test_match_kind_limits_1() {
+    // An nfa builds the dfa that finds its preferred matches within the default limits
+    let mut automaton = Automaton::from_string("(?:a|b)*a(?:a|b){14}").unwrap();
+    automaton.set_match_kind(MatchKind::LeftmostFirst);
+    let error = LimitError::StatesExceeded(DfaLimits::default().max_states);
+    assert_eq!(automaton.try_match_prefix("ab"), Err(error));
+    assert_eq!(automaton.try_match_leftmost("ab"), Err(error));
+
+    let mut automaton = Automaton::from_string("a+?").unwrap();
+    automaton.set_match_kind(MatchKind::LeftmostFirst);
+    assert_eq!(automaton.try_match_prefix("aa"), Ok(Some(0..1)));
+    assert_eq!(automaton.try_match_leftmost("baa"), Ok(Some(1..2)));
+    assert_eq!(automaton.try_match_prefix("b"), Ok(None));
+}
+
+#[test]
+fn test_match_kind_dfa_1() {
+    // Setting the match kind of a dfa does nothing, and keeps what it was built with
+    let mut automaton = Automaton::from_string(r"\bfoo").unwrap().into_dfa();
+    automaton.set_match_kind(MatchKind::LeftmostFirst);
+    assert_eq!(automaton.match_kind(), MatchKind::LeftmostLongest);
+    let automaton = automaton.into_min_dfa();
+    assert_eq!(automaton.match_substrings("xfoo"), vec![]);
+    assert_eq!(automaton.match_substrings("x foo"), vec![(2..5)]);
+
+    let mut automaton = Automaton::from_string("(a)(b)").unwrap().into_dfa();
+    automaton.set_match_kind(MatchKind::LeftmostFirst);
+    let captures = automaton.into_min_dfa().captures("ab").unwrap();
+    assert_eq!(captures.get(1), Some(0..1));
+    assert_eq!(captures.get(2), Some(1..2));
+}
+
+#[test]
+fn test_union_1() {
+    let automaton1 = Automaton::from_string("aaab*").unwrap().into_min_dfa();
+    let automaton2 = Automaton::from_string("a+c*").unwrap().into_min_dfa();
+    let union = automaton1.union(&automaton2).into_dfa();
+    assert!(union.match_whole("aaa"));
+    assert!(union.match_whole("aaabb"));
+    assert!(union.match_whole("acc"));
+    assert!(!union.match_whole("aaabc"));
+    assert!(!union.match_whole(""));
+
+    let union = union.into_min_dfa();
+    assert!(union.match_whole("aaabb"));
+    assert!(union.match_whole("acc"));
+    assert!(!union.match_whole("aaabc"));
+}
+
+#[test]
+fn test_union_nfa_1() {
+    let automaton1 = Automaton::from_string("ab*").unwrap();
+    let automaton2 = Automaton::from_string("b+").unwrap();
+    let union = automaton1.union(&automaton2).into_min_dfa();
+    assert!(union.match_whole("a"));
+    assert!(union.match_whole("abb"));
+    assert!(union.match_whole("bb"));
+    assert!(!union.match_whole("ba"));
+    assert!(!union.match_whole(""));
+
+    // An nfa and a dfa
+    let automaton2 = Automaton::from_string("").unwrap().into_dfa();
+    let union = automaton1.union(&automaton2).into_min_dfa();
+    assert!(union.match_whole(""));
+    assert!(union.match_whole("ab"));
+    assert!(!union.match_whole("b"));
+}
+
+#[test]
+fn test_union_captures_1() {
+    // The capture groups of the second nfa are numbered after the ones of the first
+    let automaton1 = Automaton::from_string("(a)b").unwrap();
+    let automaton2 = Automaton::from_string("c(?P<d>d)").unwrap();
+    let union = automaton1.union(&automaton2);
+    let captures = union.captures("cd").unwrap();
+    assert_eq!(captures.len(), 3);
+    assert_eq!(captures.get(1), None);
+    assert_eq!(captures.get(2), Some(1..2));
+    assert_eq!(captures.name("d"), Some(1..2));
+    let captures = union.into_min_dfa().captures("ab").unwrap();
+    assert_eq!(captures.get(1), Some(0..1));
+    assert_eq!(captures.get(2), None);
+
+    let config = TranslatorConfig {
+        match_kind: MatchKind::LeftmostFirst,
+        ..TranslatorConfig::default()
+    };
+    let automaton1 = Automaton::from_string_with_config("a", &config).unwrap();
+    let automaton2 = Automaton::from_string_with_config("ab", &config).unwrap();
+    let union = automaton1.union(&automaton2);
+    assert_eq!(union.match_kind(), MatchKind::LeftmostFirst);
+    assert_eq!(union.into_min_dfa().match_prefix("ab"), Some(0..1));
+}
+
+#[test]
+fn test_union_dfa_1() {
+    // A dfa without a look table is part of the nfa, which keeps the capture groups and the match kind of the nfa
+    let config = TranslatorConfig {
+        match_kind: MatchKind::LeftmostFirst,
+        ..TranslatorConfig::default()
+    };
+    let automaton1 = Automaton::from_string_with_config("(a)|(?P<b>ab)", &config).unwrap();
+    let automaton2 = Automaton::from_string("(c)").unwrap().into_min_dfa();
+    let union = automaton1.union(&automaton2);
+    assert_eq!(union.match_kind(), MatchKind::LeftmostFirst);
+    let captures = union.captures("ab").unwrap();
+    assert_eq!(captures.len(), 3);
+    assert_eq!(captures.name("b"), Some(0..2));
+    assert!(union.captures("c").is_some());
+    let union = union.into_min_dfa();
+    assert_eq!(union.match_prefix("abc"), Some(0..1));
+    assert_eq!(union.match_prefix("cab"), Some(0..1));
+}
+
+#[test]
+fn test_union_look_1() {
+    let automaton1 = Automaton::from_string(r"(?-u)a\b").unwrap().into_min_dfa();
+    let automaton2 = Automaton::from_string("b").unwrap().into_min_dfa();
+    let union = automaton1.union(&automaton2).into_min_dfa();
+    assert!(union.match_whole("a"));
+    assert!(union.match_whole("b"));
+    assert_eq!(union.match_substrings("aa a b"), vec![1..2, 3..4, 5..6]);
+}
+
+#[test]
+fn test_complement_1() {
+    let automaton = Automaton::from_string("ab*").unwrap().into_min_dfa();
+    let complement = automaton.complement().into_min_dfa();
+    assert!(complement.match_whole(""));
+    assert!(complement.match_whole("b"));
+    assert!(complement.match_whole("abc"));
+    assert!(complement.match_whole("\u{10FFFF}"));
+    assert!(!complement.match_whole("a"));
+    assert!(!complement.match_whole("abb"));
+
+    let complement = complement.complement();
+    assert!(complement.match_whole("abb"));
+    assert!(!complement.match_whole(""));
+    assert!(!complement.match_whole("abc"));
+}
+
+#[test]
+fn test_complement_over_1() {
+    let automaton = Automaton::from_string("a+").unwrap();
+    let complement = automaton
+        .complement_over(&[CharRange::new('a', 'c')])
+        .into_min_dfa();
+    assert!(complement.match_whole(""));
+    assert!(complement.match_whole("ab"));
+    assert!(complement.match_whole("cc"));
+    assert!(!complement.match_whole("aa"));
+    assert!(!complement.match_whole("d"));
+    assert!(!complement.match_whole("ad"));
+}
+
+#[test]
+fn test_complement_look_1() {
+    let automaton = Automaton::from_string(r"(?-u)\ba").unwrap().into_min_dfa();
+    let complement = automaton.complement().into_min_dfa();
+    assert!(complement.match_whole("b"));
+    assert!(!complement.match_whole("a"));
+    assert_eq!(complement.match_longest_prefix("a"), Some(0..0));
+}
+
+#[test]
+fn test_difference_1() {
+    let automaton1 = Automaton::from_string("[a-z]+").unwrap().into_min_dfa();
+    let automaton2 = Automaton::from_string("admin|root").unwrap().into_min_dfa();
+    let difference = automaton1.difference(&automaton2).into_min_dfa();
+    assert!(difference.match_whole("user"));
+    assert!(difference.match_whole("admins"));
+    assert!(!difference.match_whole("admin"));
+    assert!(!difference.match_whole("root"));
+    assert!(!difference.match_whole("User"));
+    assert!(difference.to_dot_format().starts_with("digraph"));
+
+    let difference = automaton2.difference(&automaton1).into_min_dfa();
+    assert!(!difference.match_whole("admin"));
+    assert!(!difference.match_whole(""));
+}
+
+#[test]
+fn test_symmetric_difference_1() {
+    let automaton1 = Automaton::from_string("a+b?").unwrap();
+    let automaton2 = Automaton::from_string("a?b").unwrap();
+    let symmetric_difference = automaton1.symmetric_difference(&automaton2).into_min_dfa();
+    assert!(symmetric_difference.match_whole("a"));
+    assert!(symmetric_difference.match_whole("aab"));
+    assert!(symmetric_difference.match_whole("b"));
+    assert!(!symmetric_difference.match_whole("ab"));
+    assert!(!symmetric_difference.match_whole(""));
+
+    let symmetric_difference = automaton1.symmetric_difference(&automaton1);
+    assert!(!symmetric_difference.match_whole("a"));
+    assert!(!symmetric_difference.match_whole("ab"));
+}
+
+#[test]
+fn test_concat_operation_1() {
+    let automaton1 = Automaton::from_string("a|b").unwrap().into_min_dfa();
+    let automaton2 = Automaton::from_string("c+").unwrap();
+    let concat = automaton1.concat(&automaton2).unwrap().into_min_dfa();
+    assert!(concat.match_whole("ac"));
+    assert!(concat.match_whole("bccc"));
+    assert!(!concat.match_whole("a"));
+    assert!(!concat.match_whole("c"));
+    assert!(!concat.match_whole("abc"));
+}
+
+#[test]
+fn test_star_operation_1() {
+    let automaton = Automaton::from_string("ab").unwrap().into_min_dfa();
+    let star = automaton.star().unwrap().into_min_dfa();
+    assert!(star.match_whole(""));
+    assert!(star.match_whole("ab"));
+    assert!(star.match_whole("ababab"));
+    assert!(!star.match_whole("aba"));
+}
+
+#[test]
+fn test_plus_operation_1() {
+    let automaton = Automaton::from_string("a|bc").unwrap();
+    let plus = automaton.plus().unwrap().into_min_dfa();
+    assert!(!plus.match_whole(""));
+    assert!(plus.match_whole("a"));
+    assert!(plus.match_whole("bcabc"));
+    assert!(!plus.match_whole("b"));
+}
+
+#[test]
+fn test_optional_operation_1() {
+    let automaton = Automaton::from_string("ab+").unwrap().into_min_dfa();
+    let optional = automaton.optional().unwrap().into_min_dfa();
+    assert!(optional.match_whole(""));
+    assert!(optional.match_whole("abb"));
+    assert!(!optional.match_whole("a"));
+
+    // Operations can be combined, like ```(ab+)?c*```
+    let automaton = optional
+        .concat(&Automaton::from_string("c").unwrap().star().unwrap())
+        .unwrap();
+    let automaton = automaton.into_min_dfa();
+    assert!(automaton.match_whole("abcc"));
+    assert!(automaton.match_whole("c"));
+    assert!(!automaton.match_whole("ca"));
+}
+
+#[test]
+fn test_concat_operation_look_1() {
+    // A dfa with assertions depends on the symbols around its input, which an nfa cannot keep
+    let automaton1 = Automaton::from_string(r"a\b").unwrap();
+    let automaton2 = Automaton::from_string("b").unwrap();
+    let dfa = automaton1.clone().into_min_dfa();
+    assert_eq!(dfa.concat(&automaton2).unwrap_err(), LookError);
+    assert_eq!(automaton2.concat(&dfa).unwrap_err(), LookError);
+    assert_eq!(dfa.star().unwrap_err(), LookError);
+    assert_eq!(dfa.plus().unwrap_err(), LookError);
+    assert_eq!(dfa.optional().unwrap_err(), LookError);
+
+    let concat = automaton1.concat(&automaton2).unwrap().into_min_dfa();
+    assert!(!concat.match_whole("ab"));
+    assert!(concat.is_empty());
+}
+
+#[test]
+fn test_concat_operation_captures_1() {
+    // The capture groups of the second nfa are numbered after the ones of the first
+    let automaton1 = Automaton::from_string("(a)b").unwrap();
+    let automaton2 = Automaton::from_string("c(?P<d>d)").unwrap();
+    let concat = automaton1.concat(&automaton2).unwrap();
+    let captures = concat.captures("abcd").unwrap();
+    assert_eq!(captures.len(), 3);
+    assert_eq!(captures.get(1), Some(0..1));
+    assert_eq!(captures.get(2), Some(3..4));
+    assert_eq!(captures.name("d"), Some(3..4));
+
+    let star = concat.star().unwrap().into_min_dfa();
+    let captures = star.captures("abcdabcd").unwrap();
+    assert_eq!(captures.get(1), Some(4..5));
+    assert_eq!(captures.get(2), Some(7..8));
+
+    // A dfa does not know where its capture groups start and end
+    let concat = automaton1.into_min_dfa().concat(&automaton2).unwrap();
+    let captures = concat.captures("abcd").unwrap();
+    assert_eq!(captures.len(), 2);
+    assert_eq!(captures.get(1), Some(3..4));
+
+    let automaton = leftmost_first("a|ab").optional().unwrap();
+    assert_eq!(automaton.match_kind(), MatchKind::LeftmostFirst);
+}
+
+#[test]
+fn test_equivalent_1() {
+    let automaton1 = Automaton::from_string("(a|b)*").unwrap();
+    let automaton2 = Automaton::from_string("(a*b*)*").unwrap().into_min_dfa();
+    assert_eq!(automaton1.equivalent(&automaton2), Ok(()));
+
+    let automaton1 = Automaton::from_string("[0-9]{1,3}").unwrap();
+    let automaton2 = Automaton::from_string(r"\d{1,3}").unwrap();
+    assert_eq!(
+        automaton1.equivalent(&automaton2.clone().into_min_dfa()),
+        Err("\u{660}".to_string())
+    );
+    let automaton2 = Automaton::from_string_with_config(
+        r"\d{1,3}",
+        &TranslatorConfig {
+            unicode: false,
+            ..TranslatorConfig::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(automaton1.equivalent(&automaton2), Ok(()));
+}
+
+#[test]
+fn test_equivalent_counterexample_1() {
+    // The shortest distinguishing string is found
+    let automaton1 = Automaton::from_string("a+b*").unwrap();
+    let automaton2 = Automaton::from_string("a*b+|a").unwrap();
+    assert_eq!(automaton1.equivalent(&automaton2), Err("b".to_string()));
+    assert_eq!(automaton2.equivalent(&automaton1), Err("b".to_string()));
+
+    let automaton1 = Automaton::from_string("(ab){0,3}").unwrap();
+    let automaton2 = Automaton::from_string("(ab)*").unwrap();
+    assert_eq!(
+        automaton1.equivalent(&automaton2),
+        Err("abababab".to_string())
+    );
+
+    let automaton1 = Automaton::from_string("").unwrap();
+    let automaton2 = Automaton::from_string("a").unwrap();
+    assert_eq!(automaton1.equivalent(&automaton2), Err(String::new()));
+}
+
+#[test]
+fn test_is_subset_of_1() {
+    let old_rule = Automaton::from_string("[a-z]{3,8}").unwrap().into_min_dfa();
+    let new_rule = Automaton::from_string("[a-z0-9]{2,8}").unwrap();
+    assert_eq!(old_rule.is_subset_of(&new_rule), Ok(()));
+    assert_eq!(new_rule.is_subset_of(&old_rule), Err("00".to_string()));
+    assert_eq!(old_rule.is_subset_of(&old_rule), Ok(()));
+}
+
+#[test]
+fn test_is_subset_of_witness_1() {
+    let automaton1 = Automaton::from_string("a*").unwrap();
+    let automaton2 = Automaton::from_string("a+").unwrap();
+    assert_eq!(automaton1.is_subset_of(&automaton2), Err(String::new()));
+    assert_eq!(automaton2.is_subset_of(&automaton1), Ok(()));
+
+    let automaton1 = Automaton::from_string("(ab|c)+").unwrap();
+    let automaton2 = Automaton::from_string("(ab)*c*").unwrap();
+    assert_eq!(automaton1.is_subset_of(&automaton2), Err("cab".to_string()));
+
+    // The empty language is a subset of every language
+    let empty = Automaton::new();
+    assert_eq!(empty.is_subset_of(&automaton2), Ok(()));
+}
+
+#[test]
+fn test_empty_class_1() {
+    // A class that does not match any symbol makes a pattern that does not match anything
+    for pattern in [
+        r"[a&&b]",
+        r"[a-z--a-z]",
+        r"[^\s\S]",
+        r"(?-u)[^\s\S]",
+        r"a[a&&b]",
+        r"\P{Any}",
+        r"a\P{any}+b[a&&b]",
+    ] {
+        let automaton = Automaton::from_string(pattern).unwrap();
+        assert!(automaton.is_empty(), "{}", pattern);
+        assert!(!automaton.into_min_dfa().match_whole("a"), "{}", pattern);
+    }
+
+    let automaton = Automaton::from_string(r"x|a[a&&b]*b")
+        .unwrap()
+        .into_min_dfa();
+    assert!(automaton.match_whole("x"));
+    assert!(automaton.match_whole("ab"));
+    assert!(!automaton.match_whole("aab"));
+
+    // Whether a class is empty depends on the flags it is in
+    let automaton = Automaton::from_string(r"(?i)[a&&A]").unwrap();
+    assert!(automaton.into_min_dfa().match_whole("a"));
+    let automaton = Automaton::from_string(r"(?i:[a&&A])|(?-i)[a&&A]|(?i)x[a&&A]").unwrap();
+    let automaton = automaton.into_min_dfa();
+    assert!(automaton.match_whole("a"));
+    assert!(automaton.match_whole("xA"));
+    assert!(Automaton::from_string(r"(?i:x)[a&&A]").unwrap().is_empty());
+
+    let automaton = Automaton::from_string(r"(a)|\P{Any}|(?P<name>b)").unwrap();
+    let captures = automaton.captures("b").unwrap();
+    assert_eq!(captures.len(), 3);
+    assert_eq!(captures.get(2), Some(0..1));
+    assert_eq!(captures.name("name"), Some(0..1));
+
+    let automaton = Automaton::from_string(r"(x[a&&b])?(c)").unwrap();
+    let captures = automaton.captures("c").unwrap();
+    assert_eq!(captures.len(), 3);
+    assert_eq!(captures.get(1), None);
+    assert_eq!(captures.get(2), Some(0..1));
+}
+
+#[test]
+fn test_is_empty_1() {
+    assert!(Automaton::new().is_empty());
+    assert!(Automaton::from_string(r"a\bb").unwrap().is_empty());
+    assert!(!Automaton::from_string("").unwrap().is_empty());
+    let automaton1 = Automaton::from_string("a+").unwrap().into_min_dfa();
+    let automaton2 = Automaton::from_string("b+").unwrap().into_min_dfa();
+    assert!(automaton1.intersection(&automaton2).is_empty());
+    assert!(!automaton1.union(&automaton2).is_empty());
+}
+
+#[test]
+fn test_accepts_empty_string_1() {
+    assert!(Automaton::from_string("a*").unwrap().accepts_empty_string());
+    assert!(Automaton::from_string("(a|)b?")
+        .unwrap()
+        .accepts_empty_string());
+    assert!(!Automaton::from_string("a+").unwrap().accepts_empty_string());
+    assert!(!Automaton::new().accepts_empty_string());
+}
+
+#[test]
+fn test_is_universal_1() {
+    let alphabet = [CharRange::new('a', 'b')];
+    assert!(Automaton::from_string("(a|b)*")
+        .unwrap()
+        .is_universal(&alphabet));
+    assert!(Automaton::from_string("[a-z]*")
+        .unwrap()
+        .is_universal(&alphabet));
+    assert!(!Automaton::from_string("(a|b)+")
+        .unwrap()
+        .is_universal(&alphabet));
+    assert!(!Automaton::from_string("(a|b)*")
+        .unwrap()
+        .is_universal(&[CharRange::new('a', 'c')]));
+    assert!(Automaton::from_string("(?s).*")
+        .unwrap()
+        .is_universal(&[CharRange::new('\0', char::MAX)]));
+}
+
+#[test]
+fn test_is_finite_1() {
+    assert!(Automaton::from_string("a{2,5}|bc").unwrap().is_finite());
+    assert!(Automaton::from_string("").unwrap().is_finite());
+    assert!(Automaton::new().is_finite());
+    assert!(!Automaton::from_string("ab*").unwrap().is_finite());
+    assert!(!Automaton::from_string("(ab)+c")
+        .unwrap()
+        .into_min_dfa()
+        .is_finite());
+
+    // Cycles that can not reach an accepting state do not count
+    let automaton1 = Automaton::from_string("a|b+c").unwrap().into_min_dfa();
+    let automaton2 = Automaton::from_string("a|b").unwrap().into_min_dfa();
+    assert!(automaton1.intersection(&automaton2).is_finite());
+}
+
+#[test]
+fn test_count_strings_1() {
+    let automaton = Automaton::from_string("[a-c]{2}|d").unwrap();
+    assert_eq!(automaton.count_strings(), Some(10u32.into()));
+    assert_eq!(automaton.count_strings_of_length(2), 9u32.into());
+    assert_eq!(automaton.count_strings_of_length(3), 0u32.into());
+    assert_eq!(automaton.count_strings_up_to_length(1), 1u32.into());
+
+    let automaton = Automaton::from_string("(a|ab)(c|bc)?")
+        .unwrap()
+        .into_min_dfa();
+    // a, ab, ac, abc, abbc
+    assert_eq!(automaton.count_strings(), Some(5u32.into()));
+    assert_eq!(Automaton::new().count_strings(), Some(0u32.into()));
+    assert_eq!(
+        Automaton::from_string("").unwrap().count_strings(),
+        Some(1u32.into())
+    );
+}
+
+#[test]
+fn test_count_strings_infinite_1() {
+    let automaton = Automaton::from_string("[0-9a-f]*").unwrap().into_min_dfa();
+    assert_eq!(automaton.count_strings(), None);
+    assert_eq!(automaton.count_strings_of_length(3), 4096u32.into());
+    assert_eq!(automaton.count_strings_up_to_length(2), 273u32.into());
+    assert_eq!(
+        automaton.count_strings_of_length(40).to_string(),
+        "1461501637330902918203684832716283019655932542976"
+    );
+}
+
+#[test]
+fn test_count_strings_unicode_1() {
+    // The surrogates are not symbols
+    let automaton = Automaton::from_string("(?s).").unwrap();
+    assert_eq!(automaton.count_strings(), Some(1_112_064u32.into()));
+    let automaton = Automaton::from_string(r"[\u{D000}-\u{EFFF}]").unwrap();
+    assert_eq!(automaton.count_strings(), Some(0x1800u32.into()));
+}